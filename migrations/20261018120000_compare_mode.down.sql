@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE "config" DROP COLUMN canonicalize;
+ALTER TABLE "config" DROP COLUMN compare;
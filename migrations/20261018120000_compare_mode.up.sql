@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE "config" ADD COLUMN compare text NOT NULL DEFAULT '"exact"'; -- See the "CompareMode" enum
+ALTER TABLE "config" ADD COLUMN canonicalize text; -- See the "Canonicalize" struct, json
@@ -0,0 +1,20 @@
+-- Add down migration script here
+CREATE TABLE "fetch_result_old" (
+	config										integer NOT NULL REFERENCES config(hash),
+	fetched_at								integer NOT NULL, -- seconds since unix epoch
+	created_at								integer NOT NULL, -- seconds since unix epoch
+	source_url								text NOT NULL,
+	status										text NOT NULL, -- See the "status" enum
+	body_text									text,
+	valid_json								bool -- boolean if config.try_parse_json was true
+);
+
+INSERT INTO "fetch_result_old"
+	(config, fetched_at, created_at, source_url, status, body_text, valid_json)
+SELECT
+	config, fetched_at, created_at, source_url, status, body_text, valid_json
+FROM "fetch_result"
+ORDER BY id ASC;
+
+DROP TABLE "fetch_result";
+ALTER TABLE "fetch_result_old" RENAME TO "fetch_result";
@@ -0,0 +1,23 @@
+-- Add up migration script here
+CREATE TABLE "fetch_result_new" (
+	id												integer PRIMARY KEY NOT NULL,
+	config										integer NOT NULL REFERENCES config(hash),
+	fetched_at								integer NOT NULL, -- seconds since unix epoch
+	created_at								integer NOT NULL, -- seconds since unix epoch
+	source_url								text NOT NULL,
+	status										text NOT NULL, -- See the "status" enum
+	body_text									text,
+	valid_json								bool -- boolean if config.try_parse_json was true
+);
+
+INSERT INTO "fetch_result_new"
+	(config, fetched_at, created_at, source_url, status, body_text, valid_json)
+SELECT
+	config, fetched_at, created_at, source_url, status, body_text, valid_json
+FROM "fetch_result"
+ORDER BY fetched_at ASC, rowid ASC;
+
+DROP TABLE "fetch_result";
+ALTER TABLE "fetch_result_new" RENAME TO "fetch_result";
+
+CREATE INDEX "fetch_result_config_fetched_at" ON "fetch_result" (config, fetched_at);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS "notification_delivery";
+ALTER TABLE "config" DROP COLUMN name;
@@ -0,0 +1,15 @@
+-- Add up migration script here
+ALTER TABLE "config" ADD COLUMN name text;
+
+CREATE TABLE "notification_delivery" (
+	id												integer PRIMARY KEY NOT NULL,
+	target										text NOT NULL, -- name of the notification target
+	config										integer NOT NULL REFERENCES config(hash),
+	record										integer REFERENCES fetch_result(id),
+	trigger										text NOT NULL, -- See the "Trigger" enum
+	attempt										integer NOT NULL, -- starts from 1
+	status_code								integer, -- http status returned by the target, if any
+	error											text,
+	delivered									integer NOT NULL, -- boolean
+	created_at								integer NOT NULL -- seconds since unix epoch
+);
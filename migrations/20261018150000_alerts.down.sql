@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS "alert";
+DROP TABLE IF EXISTS "fetch_sample";
@@ -0,0 +1,19 @@
+-- Add up migration script here
+CREATE TABLE "fetch_sample" (
+	config										integer NOT NULL REFERENCES config(hash),
+	fetched_at								integer NOT NULL, -- seconds since unix epoch
+	status										text NOT NULL, -- See the "status" enum
+	latency_ms								integer NOT NULL
+);
+
+CREATE INDEX "fetch_sample_config_fetched_at" ON "fetch_sample" (config, fetched_at);
+
+CREATE TABLE "alert" (
+	rule											text NOT NULL, -- name of the alert rule
+	config										integer NOT NULL REFERENCES config(hash),
+	firing										integer NOT NULL, -- boolean
+	since											integer NOT NULL, -- seconds since unix epoch
+	evaluated_at							integer NOT NULL, -- seconds since unix epoch
+	message										text NOT NULL,
+	PRIMARY KEY (rule, config)
+);
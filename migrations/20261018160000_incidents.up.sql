@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE "incident" (
+	id												integer PRIMARY KEY NOT NULL,
+	config										integer NOT NULL REFERENCES config(hash),
+	started_at								integer NOT NULL, -- seconds since unix epoch
+	ended_at									integer, -- seconds since unix epoch, null while open
+	first_status							text NOT NULL, -- See the "status" enum
+	first_error								text,
+	failed_checks							integer NOT NULL
+);
+
+CREATE INDEX "incident_config_started_at" ON "incident" (config, started_at);
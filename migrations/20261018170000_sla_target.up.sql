@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE "config" ADD COLUMN sla_target real; -- percent, for example 99.9
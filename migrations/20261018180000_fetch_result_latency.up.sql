@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE "fetch_result" ADD COLUMN latency_ms integer; -- latency of the fetch that created the record
@@ -0,0 +1,9 @@
+-- Add up migration script here
+CREATE TABLE "flap_state" (
+	config										integer PRIMARY KEY NOT NULL REFERENCES config(hash),
+	flapping									integer NOT NULL, -- boolean
+	since											integer NOT NULL, -- seconds since unix epoch
+	evaluated_at							integer NOT NULL, -- seconds since unix epoch
+	changes										integer NOT NULL, -- status changes within the detection window
+	suppressed								integer NOT NULL -- notifications held back since flapping started
+);
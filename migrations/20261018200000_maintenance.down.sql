@@ -0,0 +1,6 @@
+-- Add down migration script here
+DROP TABLE "maintenance_window";
+
+ALTER TABLE "fetch_result" DROP COLUMN maintenance;
+
+ALTER TABLE "config" DROP COLUMN tags;
@@ -0,0 +1,13 @@
+-- Add up migration script here
+ALTER TABLE "config" ADD COLUMN tags text NOT NULL DEFAULT '[]'; -- json array of strings
+
+ALTER TABLE "fetch_result" ADD COLUMN maintenance integer NOT NULL DEFAULT 0; -- boolean, fetched during a maintenance window
+
+CREATE TABLE "maintenance_window" (
+	id												integer PRIMARY KEY NOT NULL,
+	name											text NOT NULL,
+	configs										text NOT NULL, -- json array of config names or hashes
+	tags											text NOT NULL, -- json array of config tags
+	schedule									text NOT NULL, -- json, See the "MaintenanceSchedule" enum
+	from_config								integer NOT NULL -- boolean, defined in fetche.toml rather than through the api
+);
@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE "fetch_sample" DROP COLUMN assertions;
+
+ALTER TABLE "fetch_result" DROP COLUMN assertions;
+
+ALTER TABLE "config" DROP COLUMN assertions;
@@ -0,0 +1,6 @@
+-- Add up migration script here
+ALTER TABLE "config" ADD COLUMN assertions text NOT NULL DEFAULT '[]'; -- json, See the "Assertion" enum
+
+ALTER TABLE "fetch_result" ADD COLUMN assertions text; -- json, results of the config's assertions
+
+ALTER TABLE "fetch_sample" ADD COLUMN assertions text; -- json, results of the config's assertions
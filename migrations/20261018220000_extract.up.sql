@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE "config" ADD COLUMN extract text NOT NULL DEFAULT '[]'; -- json, values exported as metrics
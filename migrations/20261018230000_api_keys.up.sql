@@ -0,0 +1,8 @@
+-- Add up migration script here
+CREATE TABLE "api_key" (
+	id												integer PRIMARY KEY NOT NULL,
+	name											text NOT NULL,
+	key_hash									text NOT NULL UNIQUE, -- hex encoded sha256 of the key
+	scope											text NOT NULL, -- json, See the "ApiScope" enum
+	created_at								integer NOT NULL
+);
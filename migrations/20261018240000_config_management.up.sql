@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE "config" ADD COLUMN source text NOT NULL DEFAULT '"file"'; -- json, See the "ConfigSource" enum
+ALTER TABLE "config" ADD COLUMN paused integer NOT NULL DEFAULT 0; -- boolean, skipped by the scheduler
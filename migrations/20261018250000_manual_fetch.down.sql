@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE "fetch_result" DROP COLUMN manual;
@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE "fetch_result" ADD COLUMN manual integer NOT NULL DEFAULT 0; -- boolean, triggered through the api instead of by the schedule
@@ -0,0 +1,29 @@
+-- Add up migration script here
+CREATE TABLE "latest_state" (
+	config										integer PRIMARY KEY NOT NULL REFERENCES config(hash),
+	record										integer REFERENCES fetch_result(id), -- stored record matching the latest fetch
+	status										text NOT NULL, -- json, see Status
+	fetched_at								integer NOT NULL, -- seconds since unix epoch
+	latency_ms								integer,
+	changed_at								integer NOT NULL, -- seconds since unix epoch, last status or body change
+	last_success_at						integer, -- seconds since unix epoch, last HttpOk
+	streak										integer NOT NULL -- consecutive fetches with the current status
+);
+
+-- Start from the latest record of every config, the streak is counted from here on
+INSERT INTO "latest_state"
+	(config, record, status, fetched_at, latency_ms, changed_at, last_success_at, streak)
+SELECT
+	f.config, f.id, f.status, f.fetched_at, f.latency_ms, f.fetched_at,
+	(
+		SELECT MAX(s.fetched_at) FROM "fetch_result" s
+		WHERE s.config = f.config AND s.status LIKE '{"tag":"HttpOk"%'
+	),
+	1
+FROM "fetch_result" f
+WHERE f.id = (
+	SELECT l.id FROM "fetch_result" l
+	WHERE l.config = f.config AND l.status != '{"tag":"Unknown"}'
+	ORDER BY l.fetched_at DESC, l.id DESC
+	LIMIT 1
+);
@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE "latest_state" DROP COLUMN body_changed_at;
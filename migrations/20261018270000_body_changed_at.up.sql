@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE "latest_state" ADD COLUMN body_changed_at integer NOT NULL DEFAULT 0; -- seconds since unix epoch, last body change
+
+-- Counted from the latest status or body change
+UPDATE "latest_state" SET body_changed_at = changed_at;
@@ -0,0 +1,203 @@
+use std::sync::OnceLock;
+
+use jiff::{Timestamp, ToSpan};
+
+use crate::db::{
+    get_alert_state, get_latest_record, get_latest_samples, get_latest_state, get_samples_since,
+    save_alert_state, DBConn,
+};
+use crate::models::{
+    alert::{AlertCondition, AlertRule, AlertState},
+    config::Config,
+    extract::Extract,
+    fetch_result::Status,
+    notification::{NotificationEvent, Trigger},
+    status::LatestState,
+};
+use crate::{flap, notify};
+
+static RULES: OnceLock<Vec<AlertRule>> = OnceLock::new();
+
+/// Sets up the alert rules defined in fetche.toml
+pub fn init(rules: Vec<AlertRule>) {
+    for rule in &rules {
+        if let Err(e) = rule.validate() {
+            panic!("Invalid alert rule {e}");
+        }
+    }
+    if RULES.set(rules).is_err() {
+        tracing::warn!("Alert rules were already initialized, ignoring");
+    }
+}
+
+/// Evaluates every rule that applies to the config, should be called after each fetch
+/// - state changes are persisted and sent to the notification targets
+#[tracing::instrument(name = "alert_rules", skip_all)]
+pub async fn evaluate(db: &DBConn, config: &Config, status: Status) -> Result<(), sqlx::Error> {
+    let Some(rules) = RULES.get() else {
+        return Ok(());
+    };
+    for rule in rules.iter().filter(|rule| {
+        rule.configs.is_empty() || rule.configs.iter().any(|s| config.is_selected_by(s))
+    }) {
+        let (firing, message) = check(db, config, &rule.condition).await?;
+        let now = Timestamp::now();
+        let previous = get_alert_state(db, &rule.name, config.hash).await?;
+        // Keep the current state if the rule can't be evaluated right now
+        let firing = firing
+            .or(previous.as_ref().map(|previous| previous.firing))
+            .unwrap_or(false);
+        let changed = match &previous {
+            Some(previous) => previous.firing != firing,
+            // Nothing to resolve if the rule never fired
+            None => firing,
+        };
+        let since = match &previous {
+            Some(previous) if !changed => previous.since,
+            _ => now,
+        };
+        save_alert_state(
+            db,
+            AlertState {
+                rule: rule.name.clone(),
+                config: config.hash,
+                firing,
+                since,
+                evaluated_at: now,
+                message: message.clone(),
+            },
+        )
+        .await?;
+
+        // Held back like status changes while the config is flapping
+        if changed && !flap::suppress(db, config.hash, 1).await? {
+            let trigger = match firing {
+                true => Trigger::AlertFiring,
+                false => Trigger::AlertResolved,
+            };
+            notify::dispatch(
+                db,
+                vec![NotificationEvent {
+                    trigger,
+                    config: config.hash,
+                    config_name: config.name.clone(),
+                    source_url: config.source_url.clone(),
+                    previous_status: status,
+                    status,
+                    fetched_at: now,
+                    previous_record: None,
+                    record: None,
+                    alert: Some(rule.name.clone()),
+                    message: Some(message),
+                    latency_ms: None,
+                    diff: None,
+                }],
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether the condition holds and a message explaining why
+/// - None if there isn't enough data to tell
+async fn check(
+    db: &DBConn,
+    config: &Config,
+    condition: &AlertCondition,
+) -> Result<(Option<bool>, String), sqlx::Error> {
+    Ok(match condition {
+        AlertCondition::ConsecutiveFailures { count } => {
+            let samples = get_latest_samples(db, config.hash, i64::from(*count)).await?;
+            let failures = samples
+                .iter()
+                .take_while(|sample| sample.status.is_failure())
+                .count();
+            (
+                Some(failures >= *count as usize),
+                format!("{failures} of the last {count} fetches failed in a row"),
+            )
+        }
+        AlertCondition::Latency {
+            percentile,
+            above_ms,
+            window_s,
+        } => {
+            let since = Timestamp::now() - window_s.seconds();
+            let mut latencies: Vec<_> = get_samples_since(db, config.hash, since)
+                .await?
+                .into_iter()
+                .map(|sample| sample.latency_ms)
+                .collect();
+            if latencies.is_empty() {
+                (None, format!("No fetches in the last {window_s}s"))
+            } else {
+                let value = percentile_of(&mut latencies, *percentile);
+                (
+                    Some(value > *above_ms),
+                    format!("p{percentile} latency over the last {window_s}s is {value}ms"),
+                )
+            }
+        }
+        AlertCondition::JsonValue {
+            pointer,
+            below,
+            above,
+        } => {
+            // Read the same way as extracted metrics
+            let extract = Extract {
+                name: String::new(),
+                pointer: pointer.clone(),
+            };
+            let value = get_latest_record(db, config.hash)
+                .await?
+                .and_then(|record| record.body_text)
+                .and_then(|body| extract.read(&body));
+            match value {
+                Some(value) => (
+                    Some(
+                        below.is_some_and(|below| value < below)
+                            || above.is_some_and(|above| value > above),
+                    ),
+                    format!("{pointer} is {value}"),
+                ),
+                None => (None, format!("{pointer} is missing or not a number")),
+            }
+        }
+        AlertCondition::NoChange { for_s } => match get_latest_state(db, config.hash).await? {
+            Some(LatestState {
+                body_changed_at: changed_at,
+                ..
+            }) => {
+                let unchanged_s = Timestamp::now().as_second() - changed_at.as_second();
+                (
+                    Some(unchanged_s >= *for_s),
+                    format!("The body hasn't changed in {unchanged_s}s"),
+                )
+            }
+            None => (None, "No body recorded yet".to_owned()),
+        },
+    })
+}
+
+/// Nearest-rank percentile of a non-empty list of latencies
+fn percentile_of(latencies: &mut [i64], percentile: f64) -> i64 {
+    latencies.sort_unstable();
+    let rank = ((percentile / 100.0) * latencies.len() as f64).ceil() as usize;
+    latencies[rank.clamp(1, latencies.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_uses_the_nearest_rank() {
+        let mut latencies = vec![400, 100, 300, 200, 500];
+        assert_eq!(percentile_of(&mut latencies, 50.0), 300);
+        assert_eq!(percentile_of(&mut latencies, 80.0), 400);
+        assert_eq!(percentile_of(&mut latencies, 81.0), 500);
+        assert_eq!(percentile_of(&mut latencies, 100.0), 500);
+        assert_eq!(percentile_of(&mut latencies, 0.1), 100);
+        assert_eq!(percentile_of(&mut [250], 99.0), 250);
+    }
+}
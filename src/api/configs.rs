@@ -0,0 +1,187 @@
+use actix_web::{delete, get, post, put, web, HttpResponse};
+
+use super::{ApiError, AppState};
+use crate::db::{
+    archive_config, create_or_activate_config, get_all_configs, get_config, set_config_paused,
+};
+use crate::fetch;
+use crate::models::config::{Config, ConfigHash, ConfigInput, ConfigSource};
+use crate::models::fetch_result::PublicFetchRecord;
+use crate::probe::probe;
+
+#[derive(Debug, serde::Deserialize)]
+struct ConfigsQuery {
+    /// Only return configs that are (or aren't) active
+    #[serde(default)]
+    active: Option<bool>,
+}
+
+#[get("/configs")]
+async fn list_configs(
+    data: web::Data<AppState>,
+    query: web::Query<ConfigsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let configs: Vec<_> = get_all_configs(&data.conn)
+        .await?
+        .into_iter()
+        .filter(|config| query.active.is_none_or(|active| config.active == active))
+        .collect();
+    Ok(HttpResponse::Ok().json(configs))
+}
+
+#[get("/configs/{hash}")]
+async fn get_config_by_hash(
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+) -> Result<HttpResponse, ApiError> {
+    let config = existing_config(&data, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(config))
+}
+
+/// Creates a config that is fetched until it is archived, independent of fetche.toml
+#[post("/configs")]
+async fn create_config(
+    data: web::Data<AppState>,
+    body: web::Json<ConfigInput>,
+) -> Result<HttpResponse, ApiError> {
+    let input = body.into_inner();
+    input.validate().map_err(ApiError::BadRequest)?;
+    let mut config = Config::from(input);
+    config.source = ConfigSource::Api;
+    match get_config(&data.conn, config.hash).await? {
+        Some(existing) if existing.source == ConfigSource::File && existing.active => {
+            return Err(ApiError::Conflict(
+                "The same config is defined in fetche.toml".to_owned(),
+            ));
+        }
+        Some(existing) if existing.active => {
+            return Err(ApiError::Conflict(
+                "The same config already exists".to_owned(),
+            ));
+        }
+        // Archived configs are brought back with their history
+        Some(_) | None => {}
+    }
+    create_or_activate_config(&data.conn, config.clone()).await?;
+    let config = existing_config(&data, config.hash).await?;
+    Ok(HttpResponse::Created().json(config))
+}
+
+/// Replaces a config created through the api
+/// - changing a field that is part of the hash creates a new config and archives the old one,
+///   just like editing fetche.toml does
+#[put("/configs/{hash}")]
+async fn update_config(
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+    body: web::Json<ConfigInput>,
+) -> Result<HttpResponse, ApiError> {
+    let config_hash = path.into_inner();
+    let existing = api_config(&data, config_hash).await?;
+    let input = body.into_inner();
+    input.validate().map_err(ApiError::BadRequest)?;
+    let mut config = Config::from(input);
+    config.source = ConfigSource::Api;
+    if config.hash != config_hash {
+        let replaced = get_config(&data.conn, config.hash).await?;
+        if replaced.is_some_and(|replaced| replaced.active) {
+            return Err(ApiError::Conflict(
+                "The updated config already exists".to_owned(),
+            ));
+        }
+    }
+    // Pausing carries over to the new config
+    create_or_activate_config(&data.conn, config.clone()).await?;
+    set_config_paused(&data.conn, config.hash, existing.paused).await?;
+    if config.hash != config_hash {
+        archive_config(&data.conn, config_hash).await?;
+    }
+    let config = existing_config(&data, config.hash).await?;
+    Ok(HttpResponse::Ok().json(config))
+}
+
+#[post("/configs/{hash}/pause")]
+async fn pause_config(
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+) -> Result<HttpResponse, ApiError> {
+    set_paused(&data, path.into_inner(), true).await
+}
+
+#[post("/configs/{hash}/resume")]
+async fn resume_config(
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+) -> Result<HttpResponse, ApiError> {
+    set_paused(&data, path.into_inner(), false).await
+}
+
+/// Stops fetching a config created through the api, its records are kept
+#[delete("/configs/{hash}")]
+async fn delete_config(
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+) -> Result<HttpResponse, ApiError> {
+    let config_hash = path.into_inner();
+    api_config(&data, config_hash).await?;
+    archive_config(&data.conn, config_hash).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Fetches a config right away, outside of its schedule, and returns the outcome
+/// - the record is stored and evaluated like any other, marked as manual
+#[post("/configs/{hash}/fetch")]
+async fn fetch_config(
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+) -> Result<HttpResponse, ApiError> {
+    let config = existing_config(&data, path.into_inner()).await?;
+    if !config.active {
+        return Err(ApiError::NotFound("No such config".to_owned()));
+    }
+    let record = fetch(&data.conn, config, true).await?;
+    Ok(HttpResponse::Ok().json(PublicFetchRecord::from(record)))
+}
+
+/// Fetches a config that doesn't have to exist yet and reports what would be recorded
+/// - nothing is stored and no notifications are sent
+#[post("/probe")]
+async fn probe_config(body: web::Json<ConfigInput>) -> Result<HttpResponse, ApiError> {
+    let input = body.into_inner();
+    input.validate().map_err(ApiError::BadRequest)?;
+    Ok(HttpResponse::Ok().json(probe(&Config::from(input)).await))
+}
+
+/// Works for configs from fetche.toml as well, the pause survives restarts
+async fn set_paused(
+    data: &AppState,
+    config_hash: ConfigHash,
+    paused: bool,
+) -> Result<HttpResponse, ApiError> {
+    if !set_config_paused(&data.conn, config_hash, paused).await? {
+        return Err(ApiError::NotFound("No such config".to_owned()));
+    }
+    let config = existing_config(data, config_hash).await?;
+    Ok(HttpResponse::Ok().json(config))
+}
+
+/// The config with the given hash, active or not
+pub async fn existing_config(data: &AppState, config_hash: ConfigHash) -> Result<Config, ApiError> {
+    get_config(&data.conn, config_hash)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("No such config".to_owned()))
+}
+
+/// The active config with the given hash, if it can be changed through the api
+async fn api_config(data: &AppState, config_hash: ConfigHash) -> Result<Config, ApiError> {
+    let config = existing_config(data, config_hash).await?;
+    if !config.active {
+        return Err(ApiError::NotFound("No such config".to_owned()));
+    }
+    if config.source == ConfigSource::File {
+        return Err(ApiError::Conflict(
+            "The config is defined in fetche.toml, change it there instead".to_owned(),
+        ));
+    }
+    Ok(config)
+}
@@ -0,0 +1,48 @@
+use actix_web::{get, http::header, HttpRequest, HttpResponse};
+
+use super::AppState;
+
+/// Assets are compiled into the binary, fetche stays a single file
+const INDEX_HTML: &str = include_str!("dashboard/index.html");
+const APP_JS: &str = include_str!("dashboard/app.js");
+const APP_CSS: &str = include_str!("dashboard/app.css");
+
+/// Browsers ask for html when the dashboard is opened, api clients for json
+pub fn wants_html(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"))
+}
+
+/// Requests for the dashboard itself, which don't need an api key
+/// - the data it shows is still loaded through the api, with the key entered in the dashboard
+pub fn is_dashboard_request(req: &HttpRequest, base_path: &str) -> bool {
+    let Some(path) = req.path().strip_prefix(base_path) else {
+        return false;
+    };
+    match path {
+        "" | "/" => wants_html(req),
+        _ => path.starts_with("/dashboard/"),
+    }
+}
+
+pub fn index(data: &AppState) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(INDEX_HTML.replace("{{base_path}}", &data.base_path))
+}
+
+#[get("/dashboard/app.js")]
+async fn app_js() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/javascript; charset=utf-8")
+        .body(APP_JS)
+}
+
+#[get("/dashboard/app.css")]
+async fn app_css() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/css; charset=utf-8")
+        .body(APP_CSS)
+}
@@ -0,0 +1,238 @@
+// Dashboard of fetche, talks to the same json api as any other client
+"use strict";
+
+const basePath = document.body.dataset.basePath;
+const KEY_STORAGE = "fetche-api-key";
+const REFRESH_MS = 10000;
+const SPARKLINE_SAMPLES = 60;
+
+let selected = null;
+
+async function api(path) {
+  const headers = { Accept: "application/json" };
+  const key = localStorage.getItem(KEY_STORAGE);
+  if (key) {
+    headers.Authorization = `Bearer ${key}`;
+  }
+  const response = await fetch(`${basePath}${path}`, { headers });
+  const body = await response.json();
+  if (!response.ok) {
+    throw new Error(body.message || response.statusText);
+  }
+  return body;
+}
+
+function element(tag, props = {}, ...children) {
+  const el = document.createElement(tag);
+  Object.assign(el, props);
+  el.append(...children);
+  return el;
+}
+
+function svgElement(tag, attributes) {
+  const el = document.createElementNS("http://www.w3.org/2000/svg", tag);
+  for (const [name, value] of Object.entries(attributes)) {
+    el.setAttribute(name, value);
+  }
+  return el;
+}
+
+function statusBadge(status) {
+  const text = status.code === undefined ? status.tag : `${status.tag} ${status.code}`;
+  return element("span", { className: `badge ${status.tag}`, textContent: text });
+}
+
+function relativeTime(timestamp) {
+  const seconds = Math.round((Date.now() - Date.parse(timestamp)) / 1000);
+  const units = [
+    ["d", 86400],
+    ["h", 3600],
+    ["m", 60],
+  ];
+  for (const [unit, size] of units) {
+    if (seconds >= size) {
+      return `${Math.floor(seconds / size)}${unit} ago`;
+    }
+  }
+  return `${Math.max(seconds, 0)}s ago`;
+}
+
+function timeElement(timestamp) {
+  return element("time", {
+    dateTime: timestamp,
+    title: timestamp,
+    textContent: relativeTime(timestamp),
+  });
+}
+
+function sparkline(samples) {
+  const svg = svgElement("svg", { class: "sparkline", viewBox: "0 0 100 20", preserveAspectRatio: "none" });
+  if (samples.length < 2) {
+    return svg;
+  }
+  const latencies = samples.map((sample) => sample.latency_ms);
+  const max = Math.max(...latencies, 1);
+  const points = latencies
+    .map((latency, i) => `${(i / (latencies.length - 1)) * 100},${20 - (latency / max) * 18 - 1}`)
+    .join(" ");
+  svg.append(svgElement("polyline", { points }));
+  const title = svgElement("title", {});
+  title.textContent = `${latencies[latencies.length - 1]} ms, at most ${max} ms`;
+  svg.append(title);
+  return svg;
+}
+
+async function loadConfigs() {
+  const message = document.getElementById("message");
+  try {
+    const statuses = await api("/status");
+    const rows = await Promise.all(statuses.map(configRow));
+    document.querySelector("#configs tbody").replaceChildren(...rows);
+    document.getElementById("updated").textContent = `Updated ${new Date().toLocaleTimeString()}`;
+    message.textContent = statuses.length ? "" : "No active configs.";
+    return statuses;
+  } catch (e) {
+    message.textContent = `Couldn't load the configs: ${e.message}`;
+    return [];
+  }
+}
+
+// Feed entries link to a change as #config=HASH&from=ID&to=ID
+async function openLinkedChange(statuses) {
+  const params = new URLSearchParams(location.hash.slice(1));
+  const status = statuses.find((status) => status.config === Number(params.get("config")));
+  if (!status) {
+    return;
+  }
+  await selectConfig(status);
+  if (params.has("from") && params.has("to")) {
+    await showDiff(status.config, params.get("from"), params.get("to"));
+  }
+}
+
+async function configRow(status) {
+  const samples = await api(`/configs/${status.config}/samples?limit=${SPARKLINE_SAMPLES}`);
+  const latest = status.latest;
+  const badges = element("td");
+  if (latest) {
+    badges.append(statusBadge(latest.status));
+  }
+  if (status.paused) {
+    badges.append(" ", element("span", { className: "badge paused", textContent: "paused" }));
+  }
+  const row = element(
+    "tr",
+    {},
+    element("td", { textContent: status.name || status.source_url, title: status.source_url }),
+    badges,
+    element(
+      "td",
+      {},
+      sparkline(samples.reverse()),
+      latest && latest.latency_ms !== null ? ` ${latest.latency_ms} ms` : "",
+    ),
+    element("td", {}, latest ? timeElement(latest.changed_at) : "never"),
+    element("td", { textContent: latest ? latest.streak : "" }),
+  );
+  row.classList.toggle("selected", selected === status.config);
+  row.addEventListener("click", () => selectConfig(status));
+  return row;
+}
+
+async function selectConfig(status) {
+  selected = status.config;
+  for (const row of document.querySelectorAll("#configs tbody tr")) {
+    row.classList.toggle("selected", row.cells[0].title === status.source_url);
+  }
+  document.getElementById("detail").hidden = false;
+  document.getElementById("detail-title").textContent = status.name || status.source_url;
+  document.getElementById("detail-url").textContent = status.source_url;
+  document.getElementById("diff").replaceChildren(
+    element("p", { className: "hint", textContent: "Select a change to see what differed." }),
+  );
+  const timeline = document.getElementById("timeline");
+  try {
+    const changes = await api(`/configs/${status.config}/diffs`);
+    const items = changes.reverse().map((change) => timelineItem(status.config, change));
+    timeline.replaceChildren(...items);
+    if (!items.length) {
+      timeline.replaceChildren(element("li", { className: "hint", textContent: "No changes recorded yet." }));
+    }
+  } catch (e) {
+    timeline.replaceChildren(element("li", { textContent: `Couldn't load the history: ${e.message}` }));
+  }
+}
+
+function timelineItem(config, change) {
+  const summary = change.summary;
+  const parts = [];
+  if (summary.status_changed) {
+    parts.push("status changed");
+  }
+  if (summary.body_changed) {
+    parts.push(`+${summary.additions} -${summary.removals} ~${summary.modifications}`);
+  }
+  const item = element(
+    "li",
+    {},
+    statusBadge(change.to.status),
+    " ",
+    timeElement(change.to.fetched_at),
+    element("div", { textContent: parts.join(", ") || "no difference in the body" }),
+  );
+  item.addEventListener("click", async () => {
+    for (const other of document.querySelectorAll("#timeline li")) {
+      other.classList.toggle("selected", other === item);
+    }
+    await showDiff(config, change.from.id, change.to.id);
+  });
+  return item;
+}
+
+async function showDiff(config, from, to) {
+  const container = document.getElementById("diff");
+  try {
+    const diff = await api(`/configs/${config}/diff?from=${from}&to=${to}`);
+    const lines = diff.diff.format === "json_patch" ? patchLines(diff.diff.diff) : unifiedLines(diff.diff.diff);
+    container.replaceChildren(
+      element("p", {}, statusBadge(diff.from.status), " → ", statusBadge(diff.to.status)),
+      element("pre", {}, ...lines),
+    );
+  } catch (e) {
+    container.replaceChildren(element("p", { textContent: `Couldn't load the diff: ${e.message}` }));
+  }
+}
+
+function patchLines(patch) {
+  if (!patch.length) {
+    return [element("span", { className: "hint", textContent: "The bodies are identical." })];
+  }
+  return patch.map((op) => {
+    const value = "value" in op ? ` ${JSON.stringify(op.value)}` : "";
+    const from = "from" in op ? ` from ${op.from}` : "";
+    return element("div", { className: op.op, textContent: `${op.op} ${op.path}${from}${value}` });
+  });
+}
+
+function unifiedLines(diff) {
+  return diff.split("\n").map((line) => {
+    const className = line.startsWith("+") ? "add" : line.startsWith("-") ? "remove" : "";
+    return element("div", { className, textContent: line });
+  });
+}
+
+document.getElementById("key").addEventListener("click", () => {
+  const key = prompt("Api key, only needed if authentication is enabled", localStorage.getItem(KEY_STORAGE) || "");
+  if (key === null) {
+    return;
+  }
+  if (key.trim()) {
+    localStorage.setItem(KEY_STORAGE, key.trim());
+  } else {
+    localStorage.removeItem(KEY_STORAGE);
+  }
+  loadConfigs();
+});
+
+loadConfigs().then(openLinkedChange);
+setInterval(loadConfigs, REFRESH_MS);
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use atom_syndication::{Content, Entry, Feed, FixedDateTime, Link, Text};
+use jiff::Timestamp;
+use sqlx::SqlitePool;
+
+use super::{ApiError, AppState};
+use crate::changes::recent_changes;
+use crate::db::{get_all_configs, get_config};
+use crate::diff::render_diff;
+use crate::html::escape;
+use crate::models::config::ConfigHash;
+
+/// How many of the most recent records are looked at for the entries of a feed
+const FEED_ENTRIES: i64 = 50;
+
+/// Requests for one of the feeds, which also accept the `[auth]` feed token
+pub fn is_feed_request(req: &HttpRequest, base_path: &str) -> bool {
+    let Some(path) = req.path().strip_prefix(base_path) else {
+        return false;
+    };
+    path == "/feed.atom"
+        || path
+            .strip_prefix("/configs/")
+            .and_then(|rest| rest.strip_suffix("/feed.atom"))
+            .is_some_and(|hash| hash.parse::<ConfigHash>().is_ok())
+}
+
+#[get("/feed.atom")]
+async fn global_feed(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let feed = render_feed(&data.conn, &base_url(&req, &data.base_path), None).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/atom+xml")
+        .body(feed))
+}
+
+#[get("/configs/{hash}/feed.atom")]
+async fn config_feed(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+) -> Result<HttpResponse, ApiError> {
+    let config_hash = path.into_inner();
+    let config = get_config(&data.conn, config_hash).await?;
+    if config.is_none() {
+        return Err(ApiError::NotFound("No such config".to_owned()));
+    }
+    let feed = render_feed(
+        &data.conn,
+        &base_url(&req, &data.base_path),
+        Some(config_hash),
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/atom+xml")
+        .body(feed))
+}
+
+fn base_url(req: &HttpRequest, base_path: &str) -> String {
+    let info = req.connection_info();
+    format!("{}://{}{base_path}", info.scheme(), info.host())
+}
+
+async fn render_feed(
+    db: &SqlitePool,
+    base_url: &str,
+    filter_config: Option<ConfigHash>,
+) -> Result<String, sqlx::Error> {
+    // Most stored records are changes, so the latest ones are enough to fill the feed
+    let changes = recent_changes(db, filter_config, FEED_ENTRIES).await?;
+    let source_urls: HashMap<_, _> = get_all_configs(db)
+        .await?
+        .into_iter()
+        .map(|config| (config.hash, config.source_url))
+        .collect();
+
+    let mut entries = vec![];
+    for record_diff in changes {
+        let (from, to) = (&record_diff.from, &record_diff.to);
+        let (Some(from_id), Some(to_id)) = (from.id, to.id) else {
+            continue;
+        };
+        let source_url = source_urls
+            .get(&record_diff.config)
+            .map(String::as_str)
+            .unwrap_or_default();
+        let diff_text = render_diff(&record_diff.diff);
+        let title = if record_diff.summary.status_changed {
+            format!("{source_url}: {} -> {}", from.status, to.status)
+        } else {
+            format!("{source_url}: response changed")
+        };
+        let id = format!(
+            "{base_url}/configs/{}/diff?from={from_id}&to={to_id}",
+            record_diff.config
+        );
+        // Feed readers open links in a browser, so they point to the change in the dashboard
+        let link = format!(
+            "{base_url}/#config={}&from={from_id}&to={to_id}",
+            record_diff.config
+        );
+        entries.push(Entry {
+            id,
+            title: Text::plain(title),
+            updated: to_atom_date(to.fetched_at),
+            links: vec![Link {
+                href: link,
+                ..Default::default()
+            }],
+            content: Some(Content {
+                content_type: Some("html".to_owned()),
+                value: Some(format!(
+                    "<p>Status: {} -> {}</p><pre>{}</pre>",
+                    from.status,
+                    to.status,
+                    escape(&diff_text)
+                )),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+    }
+
+    let (id, title) = match filter_config {
+        Some(config) => (
+            format!("{base_url}/configs/{config}/feed.atom"),
+            format!("fetche: changes of {config}"),
+        ),
+        None => (
+            format!("{base_url}/feed.atom"),
+            "fetche: changes".to_owned(),
+        ),
+    };
+    let updated = entries
+        .first()
+        .map(|entry| entry.updated)
+        .unwrap_or_else(|| to_atom_date(Timestamp::now()));
+    let feed = Feed {
+        title: Text::plain(title),
+        id: id.clone(),
+        updated,
+        links: vec![Link {
+            href: id,
+            rel: "self".to_owned(),
+            ..Default::default()
+        }],
+        entries,
+        ..Default::default()
+    };
+    Ok(feed.to_string())
+}
+
+fn to_atom_date(timestamp: Timestamp) -> FixedDateTime {
+    FixedDateTime::parse_from_rfc3339(&timestamp.to_string())
+        .expect("jiff timestamps are valid rfc3339")
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn feed_requests() {
+        let is_feed =
+            |uri: &str| is_feed_request(&TestRequest::get().uri(uri).to_http_request(), "/fetche");
+        assert!(is_feed("/fetche/feed.atom"));
+        assert!(is_feed("/fetche/feed.atom?token=secret"));
+        assert!(is_feed("/fetche/configs/8432915262963637217/feed.atom"));
+        assert!(!is_feed("/feed.atom"));
+        assert!(!is_feed("/fetche/configs/8432915262963637217/diffs"));
+        assert!(!is_feed("/fetche/configs/a/b/feed.atom"));
+    }
+}
@@ -1,43 +1,155 @@
-use crate::models::config::{Config, RawConfig};
+use std::collections::HashMap;
+
+use crate::auth::require_api_key;
+use crate::changes::{latest_changes, run_changes_query, ChangesQuery};
+use crate::diff::diff_records;
+use crate::models::config::ConfigHash;
 use crate::{run_query, Query};
 use actix_cors::Cors;
-use actix_web::{get, web, App, HttpResponse, HttpServer, Responder, Scope};
+use actix_web::middleware::from_fn;
+use actix_web::{delete, get, post, web, App, HttpRequest, HttpResponse, HttpServer, Scope};
+use jiff::{Timestamp, ToSpan};
 use sqlx::SqlitePool;
 use tracing_actix_web::TracingLogger;
 
-use crate::db::connect;
+use crate::db::{
+    connect, create_maintenance_window, delete_maintenance_window, get_alert_states,
+    get_all_configs, get_config, get_deliveries, get_flap_states, get_incidents, get_latest_states,
+    get_maintenance_windows, get_record,
+};
+use crate::incident::summarize;
+use crate::models::maintenance::MaintenanceWindow;
+use crate::models::server::ServerConfig;
+use crate::models::status::ConfigStatus;
+use crate::models::uptime::UnknownAs;
+use crate::uptime::report;
+
+mod configs;
+mod dashboard;
+mod error;
+mod feed;
+mod records;
+mod status_page;
+
+pub use error::ApiError;
+pub use feed::is_feed_request;
+
+/// Requests that are answered without an api key, even if `[auth]` is enabled
+pub fn is_public_request(req: &HttpRequest, base_path: &str) -> bool {
+    dashboard::is_dashboard_request(req, base_path)
+        || status_page::is_status_page_request(req, base_path)
+}
 
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub conn: SqlitePool,
+    /// Normalized `[server]` base path, needed to build absolute links
+    pub base_path: String,
 }
 
-pub async fn run_server() -> std::io::Result<()> {
-    println!("listening for api calls on port 8080, press Ctrl+C to stop");
+pub async fn run_server(server: ServerConfig) -> std::io::Result<()> {
     let conn = connect().await;
-    let state = AppState { conn };
-    HttpServer::new(move || {
-        let cors = Cors::default()
-            .allow_any_origin()
+    let base_path = server.normalized_base_path();
+    let state = AppState {
+        conn,
+        base_path: base_path.clone(),
+    };
+    let cors_origins = server.cors_origins.clone();
+    let mut http_server = HttpServer::new(move || {
+        let mut cors = Cors::default()
             .allow_any_method()
             .allow_any_header()
             .max_age(3600);
+        if cors_origins.iter().any(|origin| origin == "*") {
+            cors = cors.allow_any_origin();
+        } else {
+            for origin in &cors_origins {
+                cors = cors.allowed_origin(origin);
+            }
+        }
         App::new()
+            .wrap(from_fn(require_api_key))
             .wrap(cors)
             .wrap(TracingLogger::default())
             .app_data(web::Data::new(state.clone()))
-            .service(routes())
-    })
-    .bind(("0.0.0.0", 8080))?
-    .run()
-    .await
+            .app_data(web::JsonConfig::default().error_handler(|e, _| bad_request(e)))
+            .app_data(web::QueryConfig::default().error_handler(|e, _| bad_request(e)))
+            .app_data(web::PathConfig::default().error_handler(|e, _| bad_request(e)))
+            .service(routes(&base_path))
+            .default_service(web::to(not_found))
+    });
+    if let Some(workers) = server.workers {
+        http_server = http_server.workers(workers);
+    }
+    for address in &server.bind {
+        http_server = match address.strip_prefix("unix:") {
+            #[cfg(unix)]
+            Some(path) => {
+                // A socket left behind by a previous run would make binding fail
+                let _ = std::fs::remove_file(path);
+                http_server.bind_uds(path)?
+            }
+            #[cfg(not(unix))]
+            Some(path) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    format!("Unix domain sockets aren't supported on this platform: {path}"),
+                ))
+            }
+            None => http_server.bind((address.as_str(), server.port))?,
+        };
+        tracing::info!(address, port = server.port, "Listening for api calls");
+    }
+    tracing::info!("Press Ctrl+C to stop");
+    http_server.run().await
+}
+
+/// Malformed bodies, queries and paths are answered with the usual json error
+fn bad_request(e: impl std::fmt::Display) -> actix_web::Error {
+    ApiError::BadRequest(e.to_string()).into()
+}
+
+async fn not_found() -> Result<HttpResponse, ApiError> {
+    Err(ApiError::NotFound("No such endpoint".to_owned()))
 }
 
-pub fn routes() -> Scope {
-    web::scope("")
+pub fn routes(base_path: &str) -> Scope {
+    web::scope(base_path)
         .service(list_configs)
+        .service(dashboard::app_js)
+        .service(dashboard::app_css)
+        .service(configs::list_configs)
+        .service(configs::get_config_by_hash)
+        .service(configs::create_config)
+        .service(configs::update_config)
+        .service(configs::pause_config)
+        .service(configs::resume_config)
+        .service(configs::delete_config)
+        .service(configs::fetch_config)
+        .service(configs::probe_config)
+        .service(records::config_records)
+        .service(records::latest_record)
+        .service(records::record_by_id)
+        .service(records::config_samples)
         .service(query_map)
         .service(query_list)
+        .service(changes)
+        .service(feed::global_feed)
+        .service(feed::config_feed)
+        .service(config_diff)
+        .service(config_diffs)
+        .service(notification_deliveries)
+        .service(alerts)
+        .service(flapping)
+        .service(status)
+        .service(status_page::status_page)
+        .service(incidents)
+        .service(incident_summary)
+        .service(config_uptime)
+        .service(metrics)
+        .service(maintenance_windows)
+        .service(create_maintenance)
+        .service(delete_maintenance)
     //.service(data_source::routes())
     //.service(meta::routes())
     //.service(user::routes())
@@ -49,37 +161,375 @@ pub fn routes() -> Scope {
     //.service(ui_utils::routes())
 }
 
+/// The dashboard when opened in a browser, all configs otherwise
 #[get("/")]
-async fn list_configs(data: web::Data<AppState>) -> impl Responder {
-    let configs = sqlx::query_as!(
-        RawConfig,
-        r#"
-       SELECT * FROM "config"
-    "#
-    )
-    .fetch_all(&data.conn)
-    .await
-    .expect("Failed to retrieve configs")
-    .into_iter()
-    .map(Config::from)
-    .collect::<Vec<_>>();
-    HttpResponse::Ok().json(configs)
+async fn list_configs(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    if dashboard::wants_html(&req) {
+        return Ok(dashboard::index(&data));
+    }
+    let configs = get_all_configs(&data.conn).await?;
+    Ok(HttpResponse::Ok().json(configs))
 }
 
 #[get("/query")]
-async fn query_map(data: web::Data<AppState>, query: web::Query<Query>) -> impl Responder {
-    let r = run_query(&data.conn, query.into_inner())
-        .await
-        .expect("Failed to run query");
-    HttpResponse::Ok().json(r)
+async fn query_map(
+    data: web::Data<AppState>,
+    query: web::Query<Query>,
+) -> Result<HttpResponse, ApiError> {
+    let r = run_query(&data.conn, query.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(r))
 }
 
 #[get("/query_list")]
-async fn query_list(data: web::Data<AppState>, query: web::Query<Query>) -> impl Responder {
-    let r = run_query(&data.conn, query.into_inner())
-        .await
-        .expect("Failed to run query");
+async fn query_list(
+    data: web::Data<AppState>,
+    query: web::Query<Query>,
+) -> Result<HttpResponse, ApiError> {
+    let r = run_query(&data.conn, query.into_inner()).await?;
     let mut values: Vec<_> = r.values().flatten().collect();
     values.sort_by_key(|r| r.fetched_at);
-    HttpResponse::Ok().json(values)
+    Ok(HttpResponse::Ok().json(values))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct DiffQuery {
+    /// id of the older record
+    from: i64,
+    /// id of the newer record
+    to: i64,
+}
+
+#[get("/configs/{hash}/diff")]
+async fn config_diff(
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+    query: web::Query<DiffQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let config_hash = path.into_inner();
+    let from = get_record(&data.conn, query.from).await?;
+    let to = get_record(&data.conn, query.to).await?;
+    match (from, to) {
+        (Some(from), Some(to)) if from.config == config_hash && to.config == config_hash => {
+            Ok(HttpResponse::Ok().json(diff_records(&from, &to)))
+        }
+        _ => Err(ApiError::NotFound(
+            "No such records for this config".to_owned(),
+        )),
+    }
+}
+
+/// Most changes returned by `/configs/{hash}/diffs` unless the query says otherwise
+const DEFAULT_DIFFS_LIMIT: i64 = 100;
+const MAX_DIFFS_LIMIT: i64 = 1000;
+/// How far back `/configs/{hash}/diffs` looks unless the query says otherwise
+const DEFAULT_DIFFS_WINDOW_DAYS: i64 = 30;
+
+#[derive(Debug, serde::Deserialize)]
+struct DiffsQuery {
+    /// Only include changes fetched at or after this time, 30 days ago by default
+    #[serde(default)]
+    since: Option<Timestamp>,
+    #[serde(default = "default_diffs_limit")]
+    limit: i64,
+}
+
+fn default_diffs_limit() -> i64 {
+    DEFAULT_DIFFS_LIMIT
+}
+
+/// The latest changes of a config, oldest first
+#[get("/configs/{hash}/diffs")]
+async fn config_diffs(
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+    query: web::Query<DiffsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    if !(1..=MAX_DIFFS_LIMIT).contains(&query.limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_DIFFS_LIMIT}"
+        )));
+    }
+    let config_hash = path.into_inner();
+    let config = get_config(&data.conn, config_hash).await?;
+    if config.is_none() {
+        return Err(ApiError::NotFound("No such config".to_owned()));
+    }
+    let since = query
+        .since
+        .unwrap_or_else(|| Timestamp::now() - (DEFAULT_DIFFS_WINDOW_DAYS * 24).hours());
+    let events = latest_changes(&data.conn, config_hash, since, query.limit).await?;
+    Ok(HttpResponse::Ok().json(events))
+}
+
+#[get("/changes")]
+async fn changes(
+    data: web::Data<AppState>,
+    query: web::Query<ChangesQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let r = run_changes_query(&data.conn, query.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(r))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct DeliveriesQuery {
+    #[serde(default = "default_deliveries_limit")]
+    limit: i64,
+}
+
+fn default_deliveries_limit() -> i64 {
+    100
+}
+
+const MAX_DELIVERIES_LIMIT: i64 = 1000;
+
+#[get("/notifications/deliveries")]
+async fn notification_deliveries(
+    data: web::Data<AppState>,
+    query: web::Query<DeliveriesQuery>,
+) -> Result<HttpResponse, ApiError> {
+    if !(1..=MAX_DELIVERIES_LIMIT).contains(&query.limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_DELIVERIES_LIMIT}"
+        )));
+    }
+    let deliveries = get_deliveries(&data.conn, query.limit).await?;
+    Ok(HttpResponse::Ok().json(deliveries))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AlertsQuery {
+    /// Only return alerts that are (or aren't) currently firing
+    #[serde(default)]
+    firing: Option<bool>,
+}
+
+#[get("/alerts")]
+async fn alerts(
+    data: web::Data<AppState>,
+    query: web::Query<AlertsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let states: Vec<_> = get_alert_states(&data.conn)
+        .await?
+        .into_iter()
+        .filter(|state| query.firing.is_none_or(|firing| state.firing == firing))
+        .collect();
+    Ok(HttpResponse::Ok().json(states))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct FlappingQuery {
+    /// Only return configs that are (or aren't) currently flapping
+    #[serde(default)]
+    flapping: Option<bool>,
+}
+
+#[get("/flapping")]
+async fn flapping(
+    data: web::Data<AppState>,
+    query: web::Query<FlappingQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let states: Vec<_> = get_flap_states(&data.conn)
+        .await?
+        .into_iter()
+        .filter(|state| {
+            query
+                .flapping
+                .is_none_or(|flapping| state.flapping == flapping)
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(states))
+}
+
+/// The latest state of every active config, cheap enough to poll for a status wall
+#[get("/status")]
+async fn status(data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let mut states: HashMap<_, _> = get_latest_states(&data.conn)
+        .await?
+        .into_iter()
+        .map(|state| (state.config, state))
+        .collect();
+    let mut statuses: Vec<_> = get_all_configs(&data.conn)
+        .await?
+        .into_iter()
+        .filter(|config| config.active)
+        .map(|config| ConfigStatus {
+            config: config.hash,
+            latest: states.remove(&config.hash),
+            name: config.name,
+            source_url: config.source_url,
+            paused: config.paused,
+        })
+        .collect();
+    statuses.sort_by(|a, b| (&a.name, &a.source_url).cmp(&(&b.name, &b.source_url)));
+    Ok(HttpResponse::Ok().json(statuses))
+}
+
+/// Window used for reliability figures unless the query says otherwise
+const DEFAULT_SUMMARY_WINDOW_DAYS: i64 = 30;
+
+#[derive(Debug, serde::Deserialize)]
+struct IncidentsQuery {
+    #[serde(default)]
+    filter_config: Option<ConfigHash>,
+    /// Only include incidents that were ongoing at or after this time
+    #[serde(default)]
+    since: Option<Timestamp>,
+    /// Only return incidents that are (or aren't) still open
+    #[serde(default)]
+    open: Option<bool>,
+}
+
+#[get("/incidents")]
+async fn incidents(
+    data: web::Data<AppState>,
+    query: web::Query<IncidentsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let since = query.since.unwrap_or(Timestamp::UNIX_EPOCH);
+    let incidents: Vec<_> = get_incidents(&data.conn, query.filter_config, since)
+        .await?
+        .into_iter()
+        .filter(|incident| {
+            query
+                .open
+                .is_none_or(|open| incident.ended_at.is_none() == open)
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(incidents))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct IncidentSummaryQuery {
+    #[serde(default)]
+    filter_config: Option<ConfigHash>,
+    /// Start of the window, 30 days ago by default
+    #[serde(default)]
+    since: Option<Timestamp>,
+    /// End of the window, now by default
+    #[serde(default)]
+    until: Option<Timestamp>,
+}
+
+#[get("/incidents/summary")]
+async fn incident_summary(
+    data: web::Data<AppState>,
+    query: web::Query<IncidentSummaryQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let until = query.until.unwrap_or_else(Timestamp::now);
+    let since = query
+        .since
+        .unwrap_or_else(|| until - (DEFAULT_SUMMARY_WINDOW_DAYS * 24).hours());
+    if since >= until || since > Timestamp::now() {
+        return Err(ApiError::BadRequest(
+            "since must be in the past and before until".to_owned(),
+        ));
+    }
+    let configs = match query.filter_config {
+        Some(config) => vec![config],
+        None => get_all_configs(&data.conn)
+            .await?
+            .into_iter()
+            .map(|config| config.hash)
+            .collect(),
+    };
+    let mut summaries = vec![];
+    for config in configs {
+        summaries.push(summarize(&data.conn, config, since, until).await?);
+    }
+    Ok(HttpResponse::Ok().json(summaries))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct UptimeQuery {
+    /// Start of the window, 30 days ago by default
+    #[serde(default)]
+    since: Option<Timestamp>,
+    /// End of the window, now by default
+    #[serde(default)]
+    until: Option<Timestamp>,
+    /// How periods when fetche wasn't running are counted, left out by default
+    #[serde(default)]
+    unknown: UnknownAs,
+}
+
+#[get("/configs/{hash}/uptime")]
+async fn config_uptime(
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+    query: web::Query<UptimeQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let config = get_config(&data.conn, path.into_inner()).await?;
+    let Some(config) = config else {
+        return Err(ApiError::NotFound("No such config".to_owned()));
+    };
+    let until = query.until.unwrap_or_else(Timestamp::now);
+    let since = query
+        .since
+        .unwrap_or_else(|| until - (DEFAULT_SUMMARY_WINDOW_DAYS * 24).hours());
+    if since >= until || since > Timestamp::now() {
+        return Err(ApiError::BadRequest(
+            "since must be in the past and before until".to_owned(),
+        ));
+    }
+    let r = report(&data.conn, &config, since, until, query.unknown).await?;
+    Ok(HttpResponse::Ok().json(r))
+}
+
+#[get("/metrics")]
+async fn metrics(data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let metrics = crate::metrics::render(&data.conn).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics))
+}
+
+#[derive(Debug, serde::Serialize)]
+struct MaintenanceWindowStatus {
+    #[serde(flatten)]
+    window: MaintenanceWindow,
+    /// Whether the window is active right now
+    active: bool,
+}
+
+#[get("/maintenance")]
+async fn maintenance_windows(data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let now = Timestamp::now();
+    let windows: Vec<_> = get_maintenance_windows(&data.conn)
+        .await?
+        .into_iter()
+        .map(|window| MaintenanceWindowStatus {
+            active: window.schedule.is_active_at(now),
+            window,
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(windows))
+}
+
+#[post("/maintenance")]
+async fn create_maintenance(
+    data: web::Data<AppState>,
+    body: web::Json<MaintenanceWindow>,
+) -> Result<HttpResponse, ApiError> {
+    let mut window = body.into_inner();
+    if let Err(e) = window.schedule.validate() {
+        return Err(ApiError::BadRequest(e.to_owned()));
+    }
+    window.from_config = false;
+    let id = create_maintenance_window(&data.conn, window.clone()).await?;
+    window.id = Some(id);
+    Ok(HttpResponse::Created().json(window))
+}
+
+#[delete("/maintenance/{id}")]
+async fn delete_maintenance(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, ApiError> {
+    let deleted = delete_maintenance_window(&data.conn, path.into_inner()).await?;
+    match deleted {
+        true => Ok(HttpResponse::NoContent().finish()),
+        false => Err(ApiError::NotFound("No such maintenance window".to_owned())),
+    }
 }
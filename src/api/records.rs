@@ -0,0 +1,101 @@
+use actix_web::{get, web, HttpResponse};
+
+use super::configs::existing_config;
+use super::{ApiError, AppState};
+use crate::db::{get_config_records_page, get_latest_record, get_latest_samples, get_record};
+use crate::models::config::ConfigHash;
+use crate::models::fetch_result::PublicFetchRecord;
+
+/// Records returned per page unless the query says otherwise
+const DEFAULT_RECORDS_LIMIT: i64 = 100;
+/// Upper bound for the page size, bodies can be large
+const MAX_RECORDS_LIMIT: i64 = 1000;
+
+#[derive(Debug, serde::Deserialize)]
+struct RecordsQuery {
+    #[serde(default = "default_records_limit")]
+    limit: i64,
+    /// Only return records older than the record with this id, for paging
+    #[serde(default)]
+    before: Option<i64>,
+}
+
+fn default_records_limit() -> i64 {
+    DEFAULT_RECORDS_LIMIT
+}
+
+/// Samples returned unless the query says otherwise
+const DEFAULT_SAMPLES_LIMIT: i64 = 60;
+
+#[derive(Debug, serde::Deserialize)]
+struct SamplesQuery {
+    #[serde(default = "default_samples_limit")]
+    limit: i64,
+}
+
+fn default_samples_limit() -> i64 {
+    DEFAULT_SAMPLES_LIMIT
+}
+
+/// A config's stored records, newest first
+#[get("/configs/{hash}/records")]
+async fn config_records(
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+    query: web::Query<RecordsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    if !(1..=MAX_RECORDS_LIMIT).contains(&query.limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_RECORDS_LIMIT}"
+        )));
+    }
+    let config = existing_config(&data, path.into_inner()).await?;
+    let records: Vec<_> =
+        get_config_records_page(&data.conn, config.hash, query.before, query.limit)
+            .await?
+            .into_iter()
+            .map(PublicFetchRecord::from)
+            .collect();
+    Ok(HttpResponse::Ok().json(records))
+}
+
+#[get("/configs/{hash}/records/latest")]
+async fn latest_record(
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+) -> Result<HttpResponse, ApiError> {
+    let config = existing_config(&data, path.into_inner()).await?;
+    let record = get_latest_record(&data.conn, config.hash)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("The config has no records yet".to_owned()))?;
+    Ok(HttpResponse::Ok().json(PublicFetchRecord::from(record)))
+}
+
+#[get("/records/{id}")]
+async fn record_by_id(
+    data: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse, ApiError> {
+    let record = get_record(&data.conn, path.into_inner())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("No such record".to_owned()))?;
+    Ok(HttpResponse::Ok().json(PublicFetchRecord::from(record)))
+}
+
+/// The outcome of a config's latest fetches, newest first
+/// - unlike records, every fetch is included, but only for a week
+#[get("/configs/{hash}/samples")]
+async fn config_samples(
+    data: web::Data<AppState>,
+    path: web::Path<ConfigHash>,
+    query: web::Query<SamplesQuery>,
+) -> Result<HttpResponse, ApiError> {
+    if !(1..=MAX_RECORDS_LIMIT).contains(&query.limit) {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {MAX_RECORDS_LIMIT}"
+        )));
+    }
+    let config = existing_config(&data, path.into_inner()).await?;
+    let samples = get_latest_samples(&data.conn, config.hash, query.limit).await?;
+    Ok(HttpResponse::Ok().json(samples))
+}
@@ -0,0 +1,174 @@
+use std::fmt::Write;
+
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use jiff::Timestamp;
+
+use super::dashboard::wants_html;
+use super::{ApiError, AppState};
+use crate::html::escape;
+use crate::models::status_page::{PublicState, StatusPage};
+use crate::status_page::{is_enabled, render};
+
+const STYLE_CSS: &str = include_str!("status_page/style.css");
+
+/// Requests for the public status page, which don't need an api key
+pub fn is_status_page_request(req: &HttpRequest, base_path: &str) -> bool {
+    is_enabled() && req.path().strip_prefix(base_path) == Some("/status-page")
+}
+
+/// The public status page as html for browsers, as json otherwise
+#[get("/status-page")]
+async fn status_page(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let page = render(&data.conn)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("The status page isn't enabled".to_owned()))?;
+    if wants_html(&req) {
+        return Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(render_html(&page)));
+    }
+    Ok(HttpResponse::Ok().json(page))
+}
+
+fn render_html(page: &StatusPage) -> String {
+    let mut html = String::new();
+    let title = escape(&page.title);
+    write!(
+        html,
+        r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta http-equiv="refresh" content="60">
+<title>{title}</title>
+<style>{STYLE_CSS}</style>
+</head>
+<body>
+<h1>{title}</h1>
+<div class="banner {}">{}</div>
+"#,
+        state_class(page.state),
+        banner(page.state),
+    )
+    .unwrap();
+
+    let mut incidents = vec![];
+    for group in &page.groups {
+        write!(html, r#"<section><h2>{}</h2>"#, escape(&group.name)).unwrap();
+        for component in &group.components {
+            write!(
+                html,
+                r#"<div class="component"><div class="row"><strong>{}</strong><span class="state {}">{}</span></div><div class="bars">"#,
+                escape(&component.name),
+                state_class(component.state),
+                component.state.label(),
+            )
+            .unwrap();
+            for day in &component.days {
+                let (level, label) = match day.availability_percent {
+                    None => ("none", "No data".to_owned()),
+                    Some(percent) => (bar_level(percent), format!("{percent:.2}%")),
+                };
+                write!(
+                    html,
+                    r#"<span class="bar {level}" title="{}: {label}"></span>"#,
+                    day.date
+                )
+                .unwrap();
+            }
+            let availability = component
+                .availability_percent
+                .map(|percent| format!("{percent:.2}% uptime"))
+                .unwrap_or_else(|| "No data".to_owned());
+            write!(
+                html,
+                r#"</div><div class="legend"><span>{} days ago</span><span>{availability}</span><span>Today</span></div></div>"#,
+                component.days.len(),
+            )
+            .unwrap();
+            incidents.extend(
+                component
+                    .incidents
+                    .iter()
+                    .map(|incident| (&component.name, incident)),
+            );
+        }
+        html.push_str("</section>\n");
+    }
+
+    incidents.sort_by_key(|(_, incident)| std::cmp::Reverse(incident.started_at));
+    html.push_str("<section><h2>Past incidents</h2>");
+    if incidents.is_empty() {
+        html.push_str("<p>No incidents reported.</p>");
+    } else {
+        html.push_str(r#"<ul class="incidents">"#);
+        for (name, incident) in incidents {
+            let duration = match (incident.ended_at, incident.duration_s) {
+                (Some(_), Some(duration_s)) => format!("lasted {}", format_duration(duration_s)),
+                _ => "ongoing".to_owned(),
+            };
+            write!(
+                html,
+                r#"<li><strong>{}</strong> <time datetime="{}">{}</time> <span class="duration">{duration}</span></li>"#,
+                escape(name),
+                incident.started_at,
+                format_time(incident.started_at),
+            )
+            .unwrap();
+        }
+        html.push_str("</ul>");
+    }
+    write!(
+        html,
+        "</section>\n<footer>Updated {}</footer>\n</body>\n</html>\n",
+        format_time(page.generated_at)
+    )
+    .unwrap();
+    html
+}
+
+fn banner(state: PublicState) -> &'static str {
+    match state {
+        PublicState::Operational => "All systems operational",
+        PublicState::Unknown => "The current status is unknown",
+        PublicState::Maintenance => "Maintenance in progress",
+        PublicState::Degraded => "Some systems are degraded",
+        PublicState::Down => "Some systems are down",
+    }
+}
+
+fn state_class(state: PublicState) -> &'static str {
+    match state {
+        PublicState::Operational => "operational",
+        PublicState::Unknown => "unknown",
+        PublicState::Maintenance => "maintenance",
+        PublicState::Degraded => "degraded",
+        PublicState::Down => "down",
+    }
+}
+
+fn bar_level(percent: f64) -> &'static str {
+    if percent >= 99.9 {
+        "up"
+    } else if percent >= 95.0 {
+        "partial"
+    } else {
+        "down"
+    }
+}
+
+fn format_time(at: Timestamp) -> String {
+    at.strftime("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+fn format_duration(seconds: i64) -> String {
+    match seconds {
+        s if s >= 3600 => format!("{}h {}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
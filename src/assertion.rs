@@ -0,0 +1,261 @@
+use regex::Regex;
+use reqwest::header::HeaderMap;
+
+use crate::models::{
+    assertion::{Assertion, AssertionResult},
+    fetch_result::Status,
+};
+
+/// What assertions are checked against
+pub struct Response<'a> {
+    pub status_code: u16,
+    pub headers: &'a HeaderMap,
+    pub body: Option<&'a str>,
+    pub latency_ms: i64,
+}
+
+/// Checks every assertion against the response, in order
+pub fn check(assertions: &[Assertion], response: &Response) -> Vec<AssertionResult> {
+    assertions
+        .iter()
+        .map(|assertion| {
+            let message = check_one(assertion, response).err();
+            AssertionResult {
+                assertion: assertion.clone(),
+                passed: message.is_none(),
+                message,
+            }
+        })
+        .collect()
+}
+
+/// Works out the status of a response from its http status and assertion results
+/// - a status assertion decides between HttpOk and HttpErr instead of the usual 2xx check
+/// - an HttpOk response failing any other assertion is AssertionFailed
+pub fn status(status_code: u16, results: &[AssertionResult]) -> Status {
+    let status_result = results
+        .iter()
+        .find(|result| matches!(result.assertion, Assertion::Status { .. }));
+    let ok = match status_result {
+        Some(result) => result.passed,
+        None => (200..300).contains(&status_code),
+    };
+    if !ok {
+        return Status::HttpErr(status_code);
+    }
+    match results.iter().all(|result| result.passed) {
+        true => Status::HttpOk(status_code),
+        false => Status::AssertionFailed(status_code),
+    }
+}
+
+fn check_one(assertion: &Assertion, response: &Response) -> Result<(), String> {
+    let body = response.body.unwrap_or_default();
+    match assertion {
+        Assertion::Status { expected } => match expected.contains(&response.status_code) {
+            true => Ok(()),
+            false => Err(format!("Got status {}", response.status_code)),
+        },
+        Assertion::BodyContains { text } => match body.contains(text.as_str()) {
+            true => Ok(()),
+            false => Err(format!("Body doesn't contain {text:?}")),
+        },
+        Assertion::BodyMatches { regex } => {
+            let regex = Regex::new(regex).map_err(|e| format!("Invalid regex: {e}"))?;
+            match regex.is_match(body) {
+                true => Ok(()),
+                false => Err(format!("Body doesn't match {regex}")),
+            }
+        }
+        Assertion::JsonPointer {
+            pointer,
+            equals,
+            min,
+            max,
+        } => {
+            let json: serde_json::Value =
+                serde_json::from_str(body).map_err(|e| format!("Body isn't valid json: {e}"))?;
+            let value = json
+                .pointer(pointer)
+                .ok_or_else(|| format!("Nothing at {pointer}"))?;
+            if let Some(expected) = equals {
+                if !json_values_equal(value, expected) {
+                    return Err(format!("{pointer} is {value}, expected {expected}"));
+                }
+            }
+            if min.is_some() || max.is_some() {
+                let number = value
+                    .as_f64()
+                    .ok_or_else(|| format!("{pointer} is {value}, not a number"))?;
+                if min.is_some_and(|min| number < min) || max.is_some_and(|max| number > max) {
+                    return Err(format!(
+                        "{pointer} is {number}, outside of the expected range"
+                    ));
+                }
+            }
+            Ok(())
+        }
+        Assertion::Header { name, equals } => {
+            let value = response
+                .headers
+                .get(name)
+                .ok_or_else(|| format!("No {name} header"))?;
+            match equals {
+                Some(expected) if value.to_str().ok() != Some(expected.as_str()) => {
+                    Err(format!("{name} header is {value:?}"))
+                }
+                _ => Ok(()),
+            }
+        }
+        Assertion::MaxLatency { ms } => match response.latency_ms <= *ms {
+            true => Ok(()),
+            false => Err(format!("Took {} ms", response.latency_ms)),
+        },
+        Assertion::MaxBodySize { bytes } => match body.len() <= *bytes {
+            true => Ok(()),
+            false => Err(format!("Body is {} bytes", body.len())),
+        },
+    }
+}
+
+/// Numbers are compared by value, so that 1 equals 1.0
+fn json_values_equal(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+    use serde_json::json;
+
+    use super::*;
+
+    fn response<'a>(status_code: u16, headers: &'a HeaderMap, body: &'a str) -> Response<'a> {
+        Response {
+            status_code,
+            headers,
+            body: Some(body),
+            latency_ms: 120,
+        }
+    }
+
+    fn pointer(
+        pointer: &str,
+        equals: Option<serde_json::Value>,
+        min: Option<f64>,
+        max: Option<f64>,
+    ) -> Assertion {
+        Assertion::JsonPointer {
+            pointer: pointer.to_owned(),
+            equals,
+            min,
+            max,
+        }
+    }
+
+    #[test]
+    fn each_assertion_reports_what_it_found() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        let body = r#"{"items":[{"price":12.5}],"ok":true,"count":3}"#;
+        let assertions = [
+            Assertion::BodyContains {
+                text: "price".to_owned(),
+            },
+            Assertion::BodyMatches {
+                regex: r#""count":\d+"#.to_owned(),
+            },
+            pointer("/items/0/price", None, Some(10.0), Some(20.0)),
+            pointer("/count", Some(json!(3.0)), None, None),
+            pointer("/ok", Some(json!(false)), None, None),
+            pointer("/missing", None, None, None),
+            Assertion::Header {
+                name: "content-type".to_owned(),
+                equals: Some("text/html".to_owned()),
+            },
+            Assertion::MaxLatency { ms: 100 },
+            Assertion::MaxBodySize { bytes: 1024 },
+        ];
+        let results = check(&assertions, &response(200, &headers, body));
+        let outcomes: Vec<_> = results
+            .iter()
+            .map(|result| (result.passed, result.message.as_deref()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (true, None),
+                (true, None),
+                (true, None),
+                (true, None),
+                (false, Some("/ok is true, expected false")),
+                (false, Some("Nothing at /missing")),
+                (false, Some(r#"content-type header is "application/json""#)),
+                (false, Some("Took 120 ms")),
+                (true, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_outside_of_the_range_fail() {
+        let headers = HeaderMap::new();
+        let results = check(
+            &[pointer("/price", None, Some(1.0), Some(5.0))],
+            &response(200, &headers, r#"{"price":7}"#),
+        );
+        assert!(!results[0].passed);
+        assert_eq!(
+            results[0].message.as_deref(),
+            Some("/price is 7, outside of the expected range")
+        );
+    }
+
+    #[test]
+    fn status_assertions_replace_the_2xx_check() {
+        let headers = HeaderMap::new();
+        let expect_404 = [Assertion::Status {
+            expected: vec![404],
+        }];
+        let results = check(&expect_404, &response(404, &headers, ""));
+        assert_eq!(status(404, &results), Status::HttpOk(404));
+        let results = check(&expect_404, &response(200, &headers, ""));
+        assert_eq!(status(200, &results), Status::HttpErr(200));
+        assert_eq!(status(500, &[]), Status::HttpErr(500));
+        assert_eq!(status(204, &[]), Status::HttpOk(204));
+    }
+
+    #[test]
+    fn other_failures_turn_ok_responses_into_assertion_failures() {
+        let headers = HeaderMap::new();
+        let contains = [Assertion::BodyContains {
+            text: "ok".to_owned(),
+        }];
+        let results = check(&contains, &response(200, &headers, "error"));
+        assert_eq!(status(200, &results), Status::AssertionFailed(200));
+        // The http status wins over the other assertions
+        let results = check(&contains, &response(503, &headers, "error"));
+        assert_eq!(status(503, &results), Status::HttpErr(503));
+    }
+
+    #[test]
+    fn invalid_regexes_and_ranges_are_rejected() {
+        assert!(Assertion::BodyMatches {
+            regex: "(".to_owned()
+        }
+        .validate()
+        .is_err());
+        assert!(Assertion::BodyMatches {
+            regex: "a+".to_owned()
+        }
+        .validate()
+        .is_ok());
+        assert!(pointer("/n", None, Some(5.0), Some(1.0))
+            .validate()
+            .is_err());
+        assert!(pointer("/n", None, Some(1.0), Some(1.0)).validate().is_ok());
+    }
+}
@@ -0,0 +1,150 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, Method},
+    middleware::Next,
+    web, ResponseError,
+};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::api::{is_feed_request, is_public_request, ApiError, AppState};
+use crate::db::{get_api_key_by_hash, DBConn};
+use crate::models::auth::{ApiScope, AuthConfig};
+
+/// Prefix of generated keys, makes them easy to recognize
+const KEY_PREFIX: &str = "fetche_";
+
+static AUTH: OnceLock<AuthConfig> = OnceLock::new();
+
+pub fn init(config: AuthConfig) {
+    if config.feed_token.as_deref().is_some_and(str::is_empty) {
+        panic!("The [auth] feed_token can't be empty");
+    }
+    if AUTH.set(config).is_err() {
+        tracing::warn!("Api authentication was already initialized, ignoring");
+    }
+}
+
+/// A new random key, to be shown to the user once and stored by its hash
+pub fn generate_key() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{KEY_PREFIX}{}", hex::encode(bytes))
+}
+
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// The scope of a key from fetche.toml or the database, if it is valid
+async fn authenticate(
+    db: &DBConn,
+    config: &AuthConfig,
+    key: &str,
+) -> Result<Option<ApiScope>, sqlx::Error> {
+    // Compared by hash so that the time taken doesn't leak how much of a key matched
+    let key_hash = hash_key(key);
+    if let Some(configured) = config
+        .keys
+        .iter()
+        .find(|configured| hash_key(&configured.key) == key_hash)
+    {
+        return Ok(Some(configured.scope));
+    }
+    Ok(get_api_key_by_hash(db, &key_hash)
+        .await?
+        .map(|stored| stored.scope))
+}
+
+/// Reads the key from `Authorization: Bearer KEY` or `X-Api-Key: KEY`
+fn presented_key(req: &ServiceRequest) -> Option<String> {
+    let headers = req.headers();
+    if let Some(bearer) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    {
+        return Some(bearer.trim().to_owned());
+    }
+    headers
+        .get("X-Api-Key")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_owned())
+}
+
+/// Whether a feed request carries the configured feed token as `?token=...`
+fn has_feed_token(req: &ServiceRequest, config: &AuthConfig, base_path: &str) -> bool {
+    let Some(feed_token) = config.feed_token.as_deref() else {
+        return false;
+    };
+    if !is_feed_request(req.request(), base_path) {
+        return false;
+    }
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.get("token").cloned())
+        // Compared by hash, like the keys
+        .is_some_and(|token| hash_key(&token) == hash_key(feed_token))
+}
+
+/// Requests that don't change anything, allowed with a read key
+fn is_read_only(method: &Method) -> bool {
+    [Method::GET, Method::HEAD, Method::OPTIONS].contains(method)
+}
+
+/// Rejects api requests without a valid key, or with a read key for requests that change data
+/// - does nothing unless `[auth]` is enabled
+pub async fn require_api_key(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(config) = AUTH.get().filter(|config| config.enabled) else {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+    let data = req
+        .app_data::<web::Data<AppState>>()
+        .expect("No app state - programmer error")
+        .clone();
+    if is_public_request(req.request(), &data.base_path)
+        || has_feed_token(&req, config, &data.base_path)
+    {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+    let scope = match presented_key(&req) {
+        Some(key) => match authenticate(&data.conn, config, &key).await {
+            Ok(scope) => scope,
+            Err(e) => {
+                let response = ApiError::from(e).error_response();
+                return Ok(req.into_response(response).map_into_right_body());
+            }
+        },
+        None => None,
+    };
+    let denied = match scope {
+        None => Some(ApiError::Unauthorized(
+            "Missing or invalid api key".to_owned(),
+        )),
+        Some(ApiScope::Read) if !is_read_only(req.method()) => {
+            Some(ApiError::Forbidden("This api key can only read".to_owned()))
+        }
+        Some(_) => None,
+    };
+    match denied {
+        Some(error) => Ok(req
+            .into_response(error.error_response())
+            .map_into_right_body()),
+        None => next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body),
+    }
+}
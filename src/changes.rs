@@ -0,0 +1,107 @@
+use std::collections::{BTreeMap, HashMap};
+
+use jiff::Timestamp;
+use sqlx::SqlitePool;
+
+use crate::db::{
+    get_latest_change_ids, get_observed_record_before, get_recent_observed_records,
+    get_records_between, get_records_by_ids,
+};
+use crate::diff::{diff_records, record_changes, ChangeEvent, RecordDiff};
+use crate::models::{config::ConfigHash, fetch_result::FetchRecord};
+
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct ChangesQuery {
+    #[serde(default)]
+    pub filter_config: Option<ConfigHash>,
+    /// Only include changes fetched at or after this time
+    #[serde(default)]
+    pub since: Option<Timestamp>,
+    /// Only include changes fetched before this time
+    #[serde(default)]
+    pub until: Option<Timestamp>,
+}
+
+/// Lists records whose body or status differed from the previous record of the same config
+/// - records that only differ in some other way (such as valid_json) are left out
+/// - downtime records are skipped, the record after them is compared to the one before them
+/// - the first record of a config is not a change, as there is nothing to compare it to
+pub async fn run_changes_query(
+    db: &SqlitePool,
+    query: ChangesQuery,
+) -> Result<Vec<ChangeEvent>, sqlx::Error> {
+    let since = query.since.unwrap_or(Timestamp::UNIX_EPOCH);
+    let until = query.until.unwrap_or(Timestamp::MAX);
+    let records = get_records_between(db, query.filter_config, since, until).await?;
+    let mut changes: Vec<_> = changes_of(db, records)
+        .await?
+        .into_iter()
+        .map(ChangeEvent::from)
+        .collect();
+    changes.sort_by_key(|event| event.to.fetched_at);
+    Ok(changes)
+}
+
+/// The changes among the latest `limit` records, newest first, with their diffs
+/// - only looks at the latest records, so it doesn't get slower as the history grows
+pub async fn recent_changes(
+    db: &SqlitePool,
+    filter_config: Option<ConfigHash>,
+    limit: i64,
+) -> Result<Vec<RecordDiff>, sqlx::Error> {
+    let records = get_recent_observed_records(db, filter_config, limit).await?;
+    let mut changes = changes_of(db, records).await?;
+    changes.sort_by_key(|diff| std::cmp::Reverse(diff.to.fetched_at));
+    Ok(changes)
+}
+
+/// The latest `limit` changes of a config fetched since the given time, oldest first
+/// - only the records involved are loaded, so it doesn't get slower as the history grows
+pub async fn latest_changes(
+    db: &SqlitePool,
+    config_hash: ConfigHash,
+    since: Timestamp,
+    limit: i64,
+) -> Result<Vec<ChangeEvent>, sqlx::Error> {
+    let pairs = get_latest_change_ids(db, config_hash, since, limit).await?;
+    let ids: Vec<_> = pairs.iter().flat_map(|&(from, to)| [from, to]).collect();
+    let records: HashMap<_, _> = get_records_by_ids(db, &ids)
+        .await?
+        .into_iter()
+        .filter_map(|record| Some((record.id?, record)))
+        .collect();
+    let changes = pairs
+        .iter()
+        .rev()
+        .filter_map(|(from, to)| Some(diff_records(records.get(from)?, records.get(to)?)))
+        // Bodies that only differ in formatting aren't a change with semantic_json
+        .filter(|diff| diff.summary.status_changed || diff.summary.body_changed)
+        .map(ChangeEvent::from)
+        .collect();
+    Ok(changes)
+}
+
+/// Groups the records, oldest first, by config and diffs them
+/// - the change into the first record of a config is relative to the record before it
+async fn changes_of(
+    db: &SqlitePool,
+    records: Vec<FetchRecord>,
+) -> Result<Vec<RecordDiff>, sqlx::Error> {
+    let mut records_by_config = BTreeMap::new();
+    for record in records {
+        records_by_config
+            .entry(record.config)
+            .or_insert_with(Vec::new)
+            .push(record);
+    }
+
+    let mut changes = vec![];
+    for (config, mut config_records) in records_by_config {
+        let first_fetched_at = config_records[0].fetched_at;
+        if let Some(previous) = get_observed_record_before(db, config, first_fetched_at).await? {
+            config_records.insert(0, previous);
+        }
+        changes.extend(record_changes(&config_records));
+    }
+    Ok(changes)
+}
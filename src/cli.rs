@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand};
+
+use crate::auth::{generate_key, hash_key};
+use crate::db::{connect, create_api_key, delete_api_key, get_api_keys};
+use crate::models::auth::ApiScope;
+use crate::models::config::{Config, ConfigInput, FetcheConfig};
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Fetches urls on a schedule and keeps track of their responses"
+)]
+pub struct Cli {
+    /// Path of the config file
+    #[arg(long, env = "FETCHE_CONFIG_PATH", default_value = "./fetche.toml")]
+    pub config: String,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Fetch the configs and serve the api, the default
+    Run,
+    /// Manage the api keys stored in the database
+    #[command(subcommand)]
+    Keys(KeysCommand),
+    /// Fetch a config once and print what would be recorded, without storing anything
+    Probe {
+        /// Name or hash of a config in the config file
+        #[arg(required_unless_present = "file")]
+        config: Option<String>,
+        /// A toml or json file with a single config, written like a [[configs]] entry
+        #[arg(long, conflicts_with = "config")]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeysCommand {
+    /// Create a key and print it, it can't be shown again afterwards
+    Create {
+        name: String,
+        #[arg(long, value_enum, default_value_t = ApiScope::Read)]
+        scope: ApiScope,
+    },
+    /// List the stored keys, without the keys themselves
+    List,
+    /// Delete a key, requests using it are rejected right away
+    Revoke { id: i64 },
+}
+
+pub async fn keys(command: KeysCommand) {
+    let pool = connect().await;
+    sqlx::migrate!()
+        .run(&pool)
+        .await
+        .expect("Failed to run migrations");
+    match command {
+        KeysCommand::Create { name, scope } => {
+            let key = generate_key();
+            let id = create_api_key(&pool, &name, scope, &hash_key(&key))
+                .await
+                .expect("Failed to store api key");
+            println!("Created key {id} ({name}, {scope:?}):");
+            println!("{key}");
+        }
+        KeysCommand::List => {
+            let keys = get_api_keys(&pool)
+                .await
+                .expect("Failed to retrieve api keys");
+            for key in keys {
+                println!(
+                    "{}\t{}\t{:?}\tcreated {}",
+                    key.id, key.name, key.scope, key.created_at
+                );
+            }
+        }
+        KeysCommand::Revoke { id } => {
+            let deleted = delete_api_key(&pool, id)
+                .await
+                .expect("Failed to delete api key");
+            match deleted {
+                true => println!("Revoked key {id}"),
+                false => println!("No key with id {id}"),
+            }
+        }
+    }
+}
+
+/// Exits with an error if the config can't be found or is invalid
+pub async fn probe(config_path: &str, selector: Option<String>, file: Option<PathBuf>) {
+    let input = match (selector, file) {
+        (_, Some(file)) => read_config_input(&file),
+        (Some(selector), None) => {
+            let config_str = std::fs::read_to_string(config_path).expect("Failed to read config");
+            let parsed: FetcheConfig = toml::from_str(&config_str).expect("Failed to parse config");
+            let found = parsed
+                .configs
+                .into_iter()
+                .find(|input| Config::from(input.clone()).is_selected_by(&selector));
+            match found {
+                Some(input) => input,
+                None => exit(&format!("No config named {selector} in {config_path}")),
+            }
+        }
+        (None, None) => unreachable!("clap requires one of them"),
+    };
+    if let Err(e) = input.validate() {
+        exit(&e);
+    }
+    let probe = crate::probe::probe(&Config::from(input)).await;
+    println!("{}", serde_json::to_string_pretty(&probe).unwrap());
+}
+
+fn read_config_input(path: &Path) -> ConfigInput {
+    let text = std::fs::read_to_string(path).expect("Failed to read config file");
+    let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(&text).map_err(|e| e.to_string()),
+        _ => toml::from_str(&text).map_err(|e| e.to_string()),
+    };
+    parsed.unwrap_or_else(|e| exit(&format!("Invalid config in {}: {e}", path.display())))
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{message}");
+    std::process::exit(1)
+}
@@ -1,10 +1,20 @@
 use jiff::Timestamp;
 use sqlx::SqlitePool;
+use tracing::Instrument;
 
 use crate::models::{
-    config::{Config, ConfigHash, RawConfig, RawConfigHash},
+    alert::{AlertState, RawAlertState},
+    auth::{ApiKey, ApiScope, RawApiKey},
+    config::{Config, ConfigHash, ConfigSource, RawConfig, RawConfigHash},
     fetch_result::{FetchRecord, RawFetchRecord, Status},
+    flap::{FlapState, RawFlapState},
+    incident::{Incident, RawIncident},
+    maintenance::{MaintenanceWindow, RawMaintenanceWindow},
+    notification::{Delivery, NotificationEvent, RawDelivery},
+    sample::{FetchSample, RawFetchSample},
+    status::{Changes, LatestState, RawLatestState},
 };
+use crate::{flap, metrics, notify};
 
 pub type DBConn = SqlitePool;
 pub type RawTimestamp = i64;
@@ -17,10 +27,7 @@ pub fn bool_to_sqlite(bool: bool) -> RawBoolean {
     }
 }
 pub fn sqlite_to_bool(int: RawBoolean) -> bool {
-    match int {
-        1 => true,
-        _ => false,
-    }
+    int == 1
 }
 
 pub async fn connect() -> DBConn {
@@ -31,11 +38,14 @@ pub async fn connect() -> DBConn {
     pool
 }
 
-pub async fn deactivate_all_configs(db: &DBConn) -> Result<(), sqlx::Error> {
+/// Deactivates the configs from fetche.toml, the ones created through the api keep their state
+pub async fn deactivate_file_configs(db: &DBConn) -> Result<(), sqlx::Error> {
+    let file = serde_json::to_string(&ConfigSource::File).unwrap();
     sqlx::query!(
         r#"
-        UPDATE "config" SET active = false
-    "#
+        UPDATE "config" SET active = false WHERE source = $1
+    "#,
+        file
     )
     .execute(db)
     .await?;
@@ -47,28 +57,68 @@ pub async fn create_or_activate_config(db: &DBConn, config: Config) -> Result<()
     sqlx::query!(
         r#"
             INSERT INTO "config"
-                (hash, source_url, fetch_interval_s, try_parse_json, active)
+                (hash, source_url, fetch_interval_s, try_parse_json, active, compare, canonicalize, name, sla_target, tags, assertions, extract, source, paused)
             VALUES
-                ($1, $2, $3, $4, $5)
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
             ON CONFLICT(hash) DO UPDATE 
-                SET active = $5
+                SET active = $5, name = $8, sla_target = $9, tags = $10, assertions = $11, extract = $12, source = $13
         "#,
         raw.hash,
         raw.source_url,
         raw.fetch_interval_s,
         raw.try_parse_json,
-        raw.active
+        raw.active,
+        raw.compare,
+        raw.canonicalize,
+        raw.name,
+        raw.sla_target,
+        raw.tags,
+        raw.assertions,
+        raw.extract,
+        raw.source,
+        raw.paused
     )
     .execute(db)
     .await?;
     Ok(())
 }
 
+/// Returns whether the config existed
+pub async fn set_config_paused(
+    db: &DBConn,
+    config_hash: ConfigHash,
+    paused: bool,
+) -> Result<bool, sqlx::Error> {
+    let rawhash = config_hash as RawConfigHash;
+    let paused = bool_to_sqlite(paused);
+    let updated = sqlx::query!(
+        r#"UPDATE "config" SET paused = $1 WHERE hash = $2"#,
+        paused,
+        rawhash
+    )
+    .execute(db)
+    .await?;
+    Ok(updated.rows_affected() > 0)
+}
+
+/// Stops fetching the config for good, its records are kept
+pub async fn archive_config(db: &DBConn, config_hash: ConfigHash) -> Result<(), sqlx::Error> {
+    let rawhash = config_hash as RawConfigHash;
+    sqlx::query!(
+        r#"UPDATE "config" SET active = FALSE WHERE hash = $1"#,
+        rawhash
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Configs the scheduler should fetch
 pub async fn get_active_configs(db: &DBConn) -> Result<Vec<ConfigHash>, sqlx::Error> {
     let raw = sqlx::query_scalar!(
         r#"
         SELECT hash FROM "config"
-        WHERE active = TRUE
+        WHERE active = TRUE AND paused = FALSE
     "#
     )
     .fetch_all(db)
@@ -76,6 +126,21 @@ pub async fn get_active_configs(db: &DBConn) -> Result<Vec<ConfigHash>, sqlx::Er
     Ok(raw.into_iter().map(|hash| hash as ConfigHash).collect())
 }
 
+pub async fn get_all_configs(db: &DBConn) -> Result<Vec<Config>, sqlx::Error> {
+    let res = sqlx::query_as!(
+        RawConfig,
+        r#"
+       SELECT * FROM "config"
+    "#
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(Config::from)
+    .collect();
+    Ok(res)
+}
+
 pub async fn get_config(
     db: &DBConn,
     config_hash: ConfigHash,
@@ -105,7 +170,9 @@ pub async fn record_downtime(db: &DBConn, config: &Config) -> Result<Option<i64>
             //println!("[{} WARN] downtime detected!", config.hash);
             record_fetch(
                 db,
+                config,
                 FetchRecord {
+                    id: None,
                     config: config.hash,
                     fetched_at: Timestamp::new(
                         last_fetched.as_second() + config.fetch_interval_s,
@@ -117,6 +184,10 @@ pub async fn record_downtime(db: &DBConn, config: &Config) -> Result<Option<i64>
                     status: Status::Unknown,
                     body_text: None,
                     valid_json: None,
+                    latency_ms: None,
+                    maintenance: false,
+                    manual: false,
+                    assertions: None,
                     from_db: true,
                 },
             )
@@ -142,14 +213,20 @@ pub async fn record_fetch_config(db: &DBConn, config: Config) -> Result<(), sqlx
     Ok(())
 }
 
-pub async fn record_fetch(db: &DBConn, fetch: FetchRecord) -> Result<Option<i64>, sqlx::Error> {
+/// Stores the record unless it matches the latest one, returning its id if it was stored
+/// - manual fetches are always stored, so that they show up in the history
+pub async fn record_fetch(
+    db: &DBConn,
+    config: &Config,
+    fetch: FetchRecord,
+) -> Result<Option<i64>, sqlx::Error> {
     let db_config_hash = fetch.config as RawConfigHash;
     let latest_result = sqlx::query_as!(
         RawFetchRecord,
         r#"
         SELECT * FROM "fetch_result"
         WHERE "config" = $1
-        ORDER BY fetched_at DESC
+        ORDER BY fetched_at DESC, id DESC
         LIMIT 1
     "#,
         db_config_hash
@@ -159,24 +236,29 @@ pub async fn record_fetch(db: &DBConn, fetch: FetchRecord) -> Result<Option<i64>
     .map(FetchRecord::try_from)
     .map(|parse_r| parse_r.unwrap());
 
+    let (status, fetched_at, latency_ms) = (fetch.status, fetch.fetched_at, fetch.latency_ms);
+    let latest_id = latest_result.as_ref().and_then(|latest| latest.id);
     let mut skip = false;
-    let mut diff = None;
-    if let Some(latest) = latest_result {
-        diff = Some(fetch.fetched_at.as_second() - latest.fetched_at.as_second());
-        if latest == fetch {
+    let mut stored_id = None;
+    let mut changes = Changes::default();
+    if let Some(latest) = &latest_result {
+        let identical = tracing::info_span!("compare")
+            .in_scope(|| latest.matches(&fetch, config.compare, config.canonicalize.as_ref()));
+        if identical && !fetch.manual {
             //println!("[{}] identical to last result, skipping", fetch.config);
             skip = true;
         }
     }
 
     if !skip {
+        let mut stored = fetch.clone();
         let raw = RawFetchRecord::from(fetch);
-        sqlx::query!(
+        let inserted = sqlx::query!(
             r#"
                 INSERT INTO "fetch_result"
-                    (config, fetched_at, created_at, source_url, status, body_text, valid_json)
+                    (config, fetched_at, created_at, source_url, status, body_text, valid_json, latency_ms, maintenance, assertions, manual)
                 VALUES
-                    ($1, $2, $3, $4, $5, $6, $7)
+                    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             "#,
             raw.config,
             raw.fetched_at,
@@ -184,11 +266,837 @@ pub async fn record_fetch(db: &DBConn, fetch: FetchRecord) -> Result<Option<i64>
             raw.source_url,
             raw.status,
             raw.body_text,
-            raw.valid_json
+            raw.valid_json,
+            raw.latency_ms,
+            raw.maintenance,
+            raw.assertions,
+            raw.manual
         )
         .execute(db)
+        .instrument(tracing::info_span!("db_write", table = "fetch_result"))
         .await?;
+        stored.id = Some(inserted.last_insert_rowid());
+        stored_id = stored.id;
+
+        // Compare against what the source returned last time, skipping over downtime
+        let previous = match latest_result {
+            Some(latest) if latest.status == Status::Unknown => {
+                get_latest_observed_record(db, config.hash, stored.id).await?
+            }
+            latest => latest,
+        };
+        changes = match &previous {
+            Some(previous) => Changes {
+                status: previous.status != stored.status,
+                body: !previous.body_matches(&stored, config.compare, config.canonicalize.as_ref()),
+            },
+            None => Changes {
+                status: true,
+                body: true,
+            },
+        };
+        if let Some(previous) = previous {
+            if changes.body {
+                metrics::observe_body_change(config.hash);
+            }
+            let triggers = notify::detect(&previous, &stored, config);
+            let diff = if triggers.is_empty() {
+                None
+            } else {
+                Some(notify::diff_excerpt(&previous, &stored))
+            };
+            let events: Vec<_> = triggers
+                .into_iter()
+                .map(|trigger| NotificationEvent {
+                    trigger,
+                    config: config.hash,
+                    config_name: config.name.clone(),
+                    source_url: stored.source_url.clone(),
+                    previous_status: previous.status,
+                    status: stored.status,
+                    fetched_at: stored.fetched_at,
+                    previous_record: previous.id,
+                    record: stored.id,
+                    alert: None,
+                    message: None,
+                    latency_ms: stored.latency_ms,
+                    diff: diff.clone(),
+                })
+                .collect();
+            // Held back and summed up once the config settles down
+            if !stored.maintenance && !flap::suppress(db, config.hash, events.len()).await? {
+                notify::dispatch(db, events);
+            }
+        }
+    }
+
+    // Downtime says nothing about the source, like for notifications
+    if status != Status::Unknown {
+        let previous_state = get_latest_state(db, config.hash).await?;
+        let state = LatestState::after_fetch(
+            previous_state.as_ref(),
+            config.hash,
+            stored_id.or(latest_id),
+            status,
+            fetched_at,
+            latency_ms,
+            changes,
+        );
+        save_latest_state(db, state).await?;
     }
 
-    Ok(diff)
+    Ok(stored_id)
+}
+
+/// The latest record of a config where fetche actually reached the source (or failed to),
+/// ignoring the given record
+async fn get_latest_observed_record(
+    db: &DBConn,
+    config_hash: ConfigHash,
+    exclude: Option<i64>,
+) -> Result<Option<FetchRecord>, sqlx::Error> {
+    let db_config_hash = config_hash as RawConfigHash;
+    let unknown = serde_json::to_string(&Status::Unknown).unwrap();
+    let exclude = exclude.unwrap_or_default();
+    let res = sqlx::query_as!(
+        RawFetchRecord,
+        r#"
+        SELECT * FROM "fetch_result"
+        WHERE "config" = $1 AND status != $2 AND id != $3
+        ORDER BY fetched_at DESC, id DESC
+        LIMIT 1
+    "#,
+        db_config_hash,
+        unknown,
+        exclude
+    )
+    .fetch_optional(db)
+    .await?
+    .map(FetchRecord::try_from)
+    .map(|parse_r| parse_r.unwrap());
+    Ok(res)
+}
+
+pub async fn record_delivery(db: &DBConn, delivery: Delivery) -> Result<(), sqlx::Error> {
+    let raw = RawDelivery::from(delivery);
+    sqlx::query!(
+        r#"
+            INSERT INTO "notification_delivery"
+                (target, config, record, trigger, attempt, status_code, error, delivered, created_at)
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+        raw.target,
+        raw.config,
+        raw.record,
+        raw.trigger,
+        raw.attempt,
+        raw.status_code,
+        raw.error,
+        raw.delivered,
+        raw.created_at
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// The most recent delivery attempts, newest first
+pub async fn get_deliveries(db: &DBConn, limit: i64) -> Result<Vec<Delivery>, sqlx::Error> {
+    let res = sqlx::query_as!(
+        RawDelivery,
+        r#"
+        SELECT * FROM "notification_delivery"
+        ORDER BY id DESC
+        LIMIT $1
+    "#,
+        limit
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(Delivery::from)
+    .collect();
+    Ok(res)
+}
+
+pub async fn get_record(db: &DBConn, id: i64) -> Result<Option<FetchRecord>, sqlx::Error> {
+    let res = sqlx::query_as!(
+        RawFetchRecord,
+        r#"
+        SELECT * FROM "fetch_result" WHERE id = $1
+    "#,
+        id
+    )
+    .fetch_optional(db)
+    .await?
+    .map(FetchRecord::try_from)
+    .map(|parse_r| parse_r.unwrap());
+    Ok(res)
+}
+
+pub async fn get_records_by_ids(db: &DBConn, ids: &[i64]) -> Result<Vec<FetchRecord>, sqlx::Error> {
+    let ids = serde_json::to_string(ids).unwrap();
+    let res = sqlx::query_as!(
+        RawFetchRecord,
+        r#"
+        SELECT * FROM "fetch_result"
+        WHERE id IN (SELECT value FROM json_each($1))
+        ORDER BY fetched_at ASC, id ASC
+    "#,
+        ids
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(FetchRecord::try_from)
+    .map(|parse_r| parse_r.unwrap())
+    .collect();
+    Ok(res)
+}
+
+/// Ids of the latest records fetched since the given time whose status or body differ
+/// byte-for-byte from the previous record, newest first, along with the id of that record
+/// - downtime records are skipped, like for changes
+pub async fn get_latest_change_ids(
+    db: &DBConn,
+    config_hash: ConfigHash,
+    since: Timestamp,
+    limit: i64,
+) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    let db_config_hash = config_hash as RawConfigHash;
+    let since = since.as_second();
+    let unknown = serde_json::to_string(&Status::Unknown).unwrap();
+    let res = sqlx::query!(
+        r#"
+        SELECT previous_id AS "previous_id!: i64", id AS "id!: i64" FROM (
+            SELECT
+                id, fetched_at, status, body_text,
+                LAG(id) OVER ordered AS previous_id,
+                LAG(status) OVER ordered AS previous_status,
+                LAG(body_text) OVER ordered AS previous_body_text
+            FROM "fetch_result"
+            WHERE "config" = $1
+                AND status != $2
+                -- The record before the window is needed to compare the first one to
+                AND fetched_at >= COALESCE((
+                    SELECT MAX(fetched_at) FROM "fetch_result"
+                    WHERE "config" = $1 AND status != $2 AND fetched_at < $3
+                ), $3)
+            WINDOW ordered AS (ORDER BY fetched_at ASC, id ASC)
+        )
+        WHERE fetched_at >= $3
+            AND previous_id IS NOT NULL
+            AND (status IS NOT previous_status OR body_text IS NOT previous_body_text)
+        ORDER BY fetched_at DESC, id DESC
+        LIMIT $4
+    "#,
+        db_config_hash,
+        unknown,
+        since,
+        limit
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|row| (row.previous_id, row.id))
+    .collect();
+    Ok(res)
+}
+
+/// A page of a config's records, newest first
+/// - `before` is the id of the last record of the previous page
+pub async fn get_config_records_page(
+    db: &DBConn,
+    config_hash: ConfigHash,
+    before: Option<i64>,
+    limit: i64,
+) -> Result<Vec<FetchRecord>, sqlx::Error> {
+    let db_config_hash = config_hash as RawConfigHash;
+    let before = before.unwrap_or(i64::MAX);
+    let res = sqlx::query_as!(
+        RawFetchRecord,
+        r#"
+        SELECT * FROM "fetch_result"
+        WHERE "config" = $1 AND id < $2
+        ORDER BY id DESC
+        LIMIT $3
+    "#,
+        db_config_hash,
+        before,
+        limit
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(FetchRecord::try_from)
+    .map(|parse_r| parse_r.unwrap())
+    .collect();
+    Ok(res)
+}
+
+/// Records fetched within [since, until), optionally limited to a single config
+pub async fn get_records_between(
+    db: &DBConn,
+    config_hash: Option<ConfigHash>,
+    since: Timestamp,
+    until: Timestamp,
+) -> Result<Vec<FetchRecord>, sqlx::Error> {
+    let db_config_hash = config_hash.map(|hash| hash as RawConfigHash);
+    let since = since.as_second();
+    let until = until.as_second();
+    let res = sqlx::query_as!(
+        RawFetchRecord,
+        r#"
+        SELECT * FROM "fetch_result"
+        WHERE ($1 IS NULL OR "config" = $1)
+            AND fetched_at >= $2
+            AND fetched_at < $3
+        ORDER BY fetched_at ASC, id ASC
+    "#,
+        db_config_hash,
+        since,
+        until
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(FetchRecord::try_from)
+    .map(|parse_r| parse_r.unwrap())
+    .collect();
+    Ok(res)
+}
+
+/// The latest record of a config fetched before the given time
+pub async fn get_record_before(
+    db: &DBConn,
+    config_hash: ConfigHash,
+    before: Timestamp,
+) -> Result<Option<FetchRecord>, sqlx::Error> {
+    let db_config_hash = config_hash as RawConfigHash;
+    let before = before.as_second();
+    let res = sqlx::query_as!(
+        RawFetchRecord,
+        r#"
+        SELECT * FROM "fetch_result"
+        WHERE "config" = $1 AND fetched_at < $2
+        ORDER BY fetched_at DESC, id DESC
+        LIMIT 1
+    "#,
+        db_config_hash,
+        before
+    )
+    .fetch_optional(db)
+    .await?
+    .map(FetchRecord::try_from)
+    .map(|parse_r| parse_r.unwrap());
+    Ok(res)
+}
+
+/// The latest record of a config fetched before the given time, skipping downtime
+pub async fn get_observed_record_before(
+    db: &DBConn,
+    config_hash: ConfigHash,
+    before: Timestamp,
+) -> Result<Option<FetchRecord>, sqlx::Error> {
+    let db_config_hash = config_hash as RawConfigHash;
+    let before = before.as_second();
+    let unknown = serde_json::to_string(&Status::Unknown).unwrap();
+    let res = sqlx::query_as!(
+        RawFetchRecord,
+        r#"
+        SELECT * FROM "fetch_result"
+        WHERE "config" = $1 AND fetched_at < $2 AND status != $3
+        ORDER BY fetched_at DESC, id DESC
+        LIMIT 1
+    "#,
+        db_config_hash,
+        before,
+        unknown
+    )
+    .fetch_optional(db)
+    .await?
+    .map(FetchRecord::try_from)
+    .map(|parse_r| parse_r.unwrap());
+    Ok(res)
+}
+
+/// The latest records, skipping downtime, optionally limited to a single config, oldest first
+pub async fn get_recent_observed_records(
+    db: &DBConn,
+    config_hash: Option<ConfigHash>,
+    limit: i64,
+) -> Result<Vec<FetchRecord>, sqlx::Error> {
+    let db_config_hash = config_hash.map(|hash| hash as RawConfigHash);
+    let unknown = serde_json::to_string(&Status::Unknown).unwrap();
+    let mut res: Vec<_> = sqlx::query_as!(
+        RawFetchRecord,
+        r#"
+        SELECT * FROM "fetch_result"
+        WHERE ($1 IS NULL OR "config" = $1) AND status != $2
+        ORDER BY fetched_at DESC, id DESC
+        LIMIT $3
+    "#,
+        db_config_hash,
+        unknown,
+        limit
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(FetchRecord::try_from)
+    .map(|parse_r| parse_r.unwrap())
+    .collect();
+    res.reverse();
+    Ok(res)
+}
+
+pub async fn record_sample(db: &DBConn, sample: FetchSample) -> Result<(), sqlx::Error> {
+    let raw = RawFetchSample::from(sample);
+    sqlx::query!(
+        r#"
+            INSERT INTO "fetch_sample"
+                (config, fetched_at, status, latency_ms, assertions)
+            VALUES
+                ($1, $2, $3, $4, $5)
+        "#,
+        raw.config,
+        raw.fetched_at,
+        raw.status,
+        raw.latency_ms,
+        raw.assertions
+    )
+    .execute(db)
+    .instrument(tracing::info_span!("db_write", table = "fetch_sample"))
+    .await?;
+    Ok(())
+}
+
+pub async fn prune_samples(
+    db: &DBConn,
+    config_hash: ConfigHash,
+    before: Timestamp,
+) -> Result<(), sqlx::Error> {
+    let db_config_hash = config_hash as RawConfigHash;
+    let before = before.as_second();
+    sqlx::query!(
+        r#"DELETE FROM "fetch_sample" WHERE config = $1 AND fetched_at < $2"#,
+        db_config_hash,
+        before
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// The latest samples of a config, newest first
+pub async fn get_latest_samples(
+    db: &DBConn,
+    config_hash: ConfigHash,
+    limit: i64,
+) -> Result<Vec<FetchSample>, sqlx::Error> {
+    let db_config_hash = config_hash as RawConfigHash;
+    let res = sqlx::query_as!(
+        RawFetchSample,
+        r#"
+        SELECT * FROM "fetch_sample"
+        WHERE config = $1
+        ORDER BY fetched_at DESC
+        LIMIT $2
+    "#,
+        db_config_hash,
+        limit
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(FetchSample::from)
+    .collect();
+    Ok(res)
+}
+
+pub async fn get_samples_since(
+    db: &DBConn,
+    config_hash: ConfigHash,
+    since: Timestamp,
+) -> Result<Vec<FetchSample>, sqlx::Error> {
+    let db_config_hash = config_hash as RawConfigHash;
+    let since = since.as_second();
+    let res = sqlx::query_as!(
+        RawFetchSample,
+        r#"
+        SELECT * FROM "fetch_sample"
+        WHERE config = $1 AND fetched_at >= $2
+        ORDER BY fetched_at ASC
+    "#,
+        db_config_hash,
+        since
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(FetchSample::from)
+    .collect();
+    Ok(res)
+}
+
+pub async fn get_latest_record(
+    db: &DBConn,
+    config_hash: ConfigHash,
+) -> Result<Option<FetchRecord>, sqlx::Error> {
+    get_record_before(db, config_hash, Timestamp::MAX).await
+}
+
+pub async fn get_alert_state(
+    db: &DBConn,
+    rule: &str,
+    config_hash: ConfigHash,
+) -> Result<Option<AlertState>, sqlx::Error> {
+    let db_config_hash = config_hash as RawConfigHash;
+    let res = sqlx::query_as!(
+        RawAlertState,
+        r#"SELECT * FROM "alert" WHERE rule = $1 AND config = $2"#,
+        rule,
+        db_config_hash
+    )
+    .fetch_optional(db)
+    .await?
+    .map(AlertState::from);
+    Ok(res)
+}
+
+pub async fn save_alert_state(db: &DBConn, state: AlertState) -> Result<(), sqlx::Error> {
+    let raw = RawAlertState::from(state);
+    sqlx::query!(
+        r#"
+            INSERT INTO "alert"
+                (rule, config, firing, since, evaluated_at, message)
+            VALUES
+                ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT(rule, config) DO UPDATE
+                SET firing = $3, since = $4, evaluated_at = $5, message = $6
+        "#,
+        raw.rule,
+        raw.config,
+        raw.firing,
+        raw.since,
+        raw.evaluated_at,
+        raw.message
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_alert_states(db: &DBConn) -> Result<Vec<AlertState>, sqlx::Error> {
+    let res = sqlx::query_as!(
+        RawAlertState,
+        r#"SELECT * FROM "alert" ORDER BY since DESC"#
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(AlertState::from)
+    .collect();
+    Ok(res)
+}
+
+pub async fn get_flap_state(
+    db: &DBConn,
+    config_hash: ConfigHash,
+) -> Result<Option<FlapState>, sqlx::Error> {
+    let db_config_hash = config_hash as RawConfigHash;
+    let res = sqlx::query_as!(
+        RawFlapState,
+        r#"SELECT * FROM "flap_state" WHERE config = $1"#,
+        db_config_hash
+    )
+    .fetch_optional(db)
+    .await?
+    .map(FlapState::from);
+    Ok(res)
+}
+
+pub async fn save_flap_state(db: &DBConn, state: FlapState) -> Result<(), sqlx::Error> {
+    let raw = RawFlapState::from(state);
+    sqlx::query!(
+        r#"
+            INSERT INTO "flap_state"
+                (config, flapping, since, evaluated_at, changes, suppressed)
+            VALUES
+                ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT(config) DO UPDATE
+                SET flapping = $2, since = $3, evaluated_at = $4, changes = $5, suppressed = $6
+        "#,
+        raw.config,
+        raw.flapping,
+        raw.since,
+        raw.evaluated_at,
+        raw.changes,
+        raw.suppressed
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_flap_states(db: &DBConn) -> Result<Vec<FlapState>, sqlx::Error> {
+    let res = sqlx::query_as!(
+        RawFlapState,
+        r#"SELECT * FROM "flap_state" ORDER BY since DESC"#
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(FlapState::from)
+    .collect();
+    Ok(res)
+}
+
+pub async fn get_latest_state(
+    db: &DBConn,
+    config_hash: ConfigHash,
+) -> Result<Option<LatestState>, sqlx::Error> {
+    let db_config_hash = config_hash as RawConfigHash;
+    let res = sqlx::query_as!(
+        RawLatestState,
+        r#"SELECT * FROM "latest_state" WHERE config = $1"#,
+        db_config_hash
+    )
+    .fetch_optional(db)
+    .await?
+    .map(LatestState::from);
+    Ok(res)
+}
+
+pub async fn save_latest_state(db: &DBConn, state: LatestState) -> Result<(), sqlx::Error> {
+    let raw = RawLatestState::from(state);
+    sqlx::query!(
+        r#"
+            INSERT INTO "latest_state"
+                (config, record, status, fetched_at, latency_ms, changed_at, last_success_at, streak, body_changed_at)
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT(config) DO UPDATE
+                SET record = $2, status = $3, fetched_at = $4, latency_ms = $5,
+                    changed_at = $6, last_success_at = $7, streak = $8, body_changed_at = $9
+        "#,
+        raw.config,
+        raw.record,
+        raw.status,
+        raw.fetched_at,
+        raw.latency_ms,
+        raw.changed_at,
+        raw.last_success_at,
+        raw.streak,
+        raw.body_changed_at
+    )
+    .execute(db)
+    .instrument(tracing::info_span!("db_write", table = "latest_state"))
+    .await?;
+    Ok(())
+}
+
+pub async fn get_latest_states(db: &DBConn) -> Result<Vec<LatestState>, sqlx::Error> {
+    let res = sqlx::query_as!(RawLatestState, r#"SELECT * FROM "latest_state""#)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(LatestState::from)
+        .collect();
+    Ok(res)
+}
+
+pub async fn get_maintenance_windows(db: &DBConn) -> Result<Vec<MaintenanceWindow>, sqlx::Error> {
+    let res = sqlx::query_as!(
+        RawMaintenanceWindow,
+        r#"SELECT * FROM "maintenance_window" ORDER BY id ASC"#
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(MaintenanceWindow::from)
+    .collect();
+    Ok(res)
+}
+
+/// Stores a new maintenance window, returning its id
+pub async fn create_maintenance_window(
+    db: &DBConn,
+    window: MaintenanceWindow,
+) -> Result<i64, sqlx::Error> {
+    let raw = RawMaintenanceWindow::from(window);
+    let inserted = sqlx::query!(
+        r#"
+            INSERT INTO "maintenance_window"
+                (name, configs, tags, schedule, from_config)
+            VALUES
+                ($1, $2, $3, $4, $5)
+        "#,
+        raw.name,
+        raw.configs,
+        raw.tags,
+        raw.schedule,
+        raw.from_config
+    )
+    .execute(db)
+    .await?;
+    Ok(inserted.last_insert_rowid())
+}
+
+/// Returns whether a window with the given id existed
+pub async fn delete_maintenance_window(db: &DBConn, id: i64) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query!(r#"DELETE FROM "maintenance_window" WHERE id = $1"#, id)
+        .execute(db)
+        .await?;
+    Ok(deleted.rows_affected() > 0)
+}
+
+pub async fn delete_config_maintenance_windows(db: &DBConn) -> Result<(), sqlx::Error> {
+    sqlx::query!(r#"DELETE FROM "maintenance_window" WHERE from_config = TRUE"#)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Stores a new api key by its hash, returning its id
+pub async fn create_api_key(
+    db: &DBConn,
+    name: &str,
+    scope: ApiScope,
+    key_hash: &str,
+) -> Result<i64, sqlx::Error> {
+    let scope = serde_json::to_string(&scope).unwrap();
+    let created_at = Timestamp::now().as_second();
+    let inserted = sqlx::query!(
+        r#"
+            INSERT INTO "api_key"
+                (name, key_hash, scope, created_at)
+            VALUES
+                ($1, $2, $3, $4)
+        "#,
+        name,
+        key_hash,
+        scope,
+        created_at
+    )
+    .execute(db)
+    .await?;
+    Ok(inserted.last_insert_rowid())
+}
+
+pub async fn get_api_keys(db: &DBConn) -> Result<Vec<ApiKey>, sqlx::Error> {
+    let res = sqlx::query_as!(RawApiKey, r#"SELECT * FROM "api_key" ORDER BY id ASC"#)
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(ApiKey::from)
+        .collect();
+    Ok(res)
+}
+
+pub async fn get_api_key_by_hash(
+    db: &DBConn,
+    key_hash: &str,
+) -> Result<Option<ApiKey>, sqlx::Error> {
+    let res = sqlx::query_as!(
+        RawApiKey,
+        r#"SELECT * FROM "api_key" WHERE key_hash = $1"#,
+        key_hash
+    )
+    .fetch_optional(db)
+    .await?
+    .map(ApiKey::from);
+    Ok(res)
+}
+
+/// Returns whether a key with the given id existed
+pub async fn delete_api_key(db: &DBConn, id: i64) -> Result<bool, sqlx::Error> {
+    let deleted = sqlx::query!(r#"DELETE FROM "api_key" WHERE id = $1"#, id)
+        .execute(db)
+        .await?;
+    Ok(deleted.rows_affected() > 0)
+}
+
+pub async fn get_open_incident(
+    db: &DBConn,
+    config_hash: ConfigHash,
+) -> Result<Option<Incident>, sqlx::Error> {
+    let db_config_hash = config_hash as RawConfigHash;
+    let res = sqlx::query_as!(
+        RawIncident,
+        r#"
+        SELECT * FROM "incident"
+        WHERE config = $1 AND ended_at IS NULL
+        ORDER BY started_at DESC
+        LIMIT 1
+    "#,
+        db_config_hash
+    )
+    .fetch_optional(db)
+    .await?
+    .map(Incident::from);
+    Ok(res)
+}
+
+pub async fn save_incident(db: &DBConn, incident: Incident) -> Result<(), sqlx::Error> {
+    let raw = RawIncident::from(incident);
+    match raw.id {
+        Some(id) => {
+            sqlx::query!(
+                r#"UPDATE "incident" SET ended_at = $2, failed_checks = $3 WHERE id = $1"#,
+                id,
+                raw.ended_at,
+                raw.failed_checks
+            )
+            .execute(db)
+            .await?;
+        }
+        None => {
+            sqlx::query!(
+                r#"
+                    INSERT INTO "incident"
+                        (config, started_at, ended_at, first_status, first_error, failed_checks)
+                    VALUES
+                        ($1, $2, $3, $4, $5, $6)
+                "#,
+                raw.config,
+                raw.started_at,
+                raw.ended_at,
+                raw.first_status,
+                raw.first_error,
+                raw.failed_checks
+            )
+            .execute(db)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Incidents that were ongoing at or after `since`, newest first
+pub async fn get_incidents(
+    db: &DBConn,
+    config_hash: Option<ConfigHash>,
+    since: Timestamp,
+) -> Result<Vec<Incident>, sqlx::Error> {
+    let db_config_hash = config_hash.map(|hash| hash as RawConfigHash);
+    let since = since.as_second();
+    let res = sqlx::query_as!(
+        RawIncident,
+        r#"
+        SELECT * FROM "incident"
+        WHERE ($1 IS NULL OR config = $1)
+            AND (ended_at IS NULL OR ended_at >= $2)
+        ORDER BY started_at DESC
+    "#,
+        db_config_hash,
+        since
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(Incident::from)
+    .collect();
+    Ok(res)
 }
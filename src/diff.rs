@@ -0,0 +1,310 @@
+use jiff::Timestamp;
+use json_patch::{Patch, PatchOperation};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+use crate::models::{
+    config::ConfigHash,
+    fetch_result::{FetchRecord, PublicFetchRecord, PublicFetchRecordBody, Status},
+    i64_as_string,
+};
+
+/// Lines of context around each hunk of a unified diff
+const UNIFIED_CONTEXT_LINES: usize = 3;
+
+/// Identifies one side of a diff without repeating the whole body
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RecordRef {
+    pub id: Option<i64>,
+    pub fetched_at: Timestamp,
+    pub status: Status,
+}
+
+impl From<&FetchRecord> for RecordRef {
+    fn from(record: &FetchRecord) -> Self {
+        Self {
+            id: record.id,
+            fetched_at: record.fetched_at,
+            status: record.status,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "format", content = "diff")]
+pub enum BodyDiff {
+    /// RFC 6902 patch turning the old body into the new one, used when both bodies are json
+    #[serde(rename = "json_patch")]
+    JsonPatch(Patch),
+    /// Unified text diff, used when either body is plain text
+    #[serde(rename = "unified")]
+    Unified(String),
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DiffSummary {
+    pub status_changed: bool,
+    pub body_changed: bool,
+    /// Added json values or lines
+    pub additions: usize,
+    /// Removed json values or lines
+    pub removals: usize,
+    /// Replaced, moved or copied json values, always 0 for text diffs
+    pub modifications: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RecordDiff {
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    pub from: RecordRef,
+    pub to: RecordRef,
+    pub summary: DiffSummary,
+    pub diff: BodyDiff,
+}
+
+/// A change between two consecutive records, without the diff itself
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChangeEvent {
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    pub from: RecordRef,
+    pub to: RecordRef,
+    pub summary: DiffSummary,
+}
+
+impl From<RecordDiff> for ChangeEvent {
+    fn from(diff: RecordDiff) -> Self {
+        Self {
+            config: diff.config,
+            from: diff.from,
+            to: diff.to,
+            summary: diff.summary,
+        }
+    }
+}
+
+pub fn diff_records(from: &FetchRecord, to: &FetchRecord) -> RecordDiff {
+    let (diff, mut summary) = diff_bodies(
+        PublicFetchRecord::from(from.clone()).data,
+        PublicFetchRecord::from(to.clone()).data,
+    );
+    summary.status_changed = from.status != to.status;
+    RecordDiff {
+        config: to.config,
+        from: RecordRef::from(from),
+        to: RecordRef::from(to),
+        summary,
+        diff,
+    }
+}
+
+/// Renders a body diff as text, json patches are pretty printed
+pub fn render_diff(diff: &BodyDiff) -> String {
+    match diff {
+        BodyDiff::JsonPatch(patch) => serde_json::to_string_pretty(patch).unwrap_or_default(),
+        BodyDiff::Unified(text) => text.clone(),
+    }
+}
+
+/// Diffs consecutive records and keeps the ones where the status or body changed, oldest first
+/// - records are expected to belong to the same config and be sorted by fetched_at
+/// - records with an Unknown status are skipped, fetche wasn't running then
+/// - records that only differ in some other way (such as manual) are left out
+pub fn record_changes(records: &[FetchRecord]) -> Vec<RecordDiff> {
+    let observed: Vec<_> = records
+        .iter()
+        .filter(|record| record.status != Status::Unknown)
+        .collect();
+    observed
+        .windows(2)
+        .map(|pair| diff_records(pair[0], pair[1]))
+        .filter(|diff| diff.summary.status_changed || diff.summary.body_changed)
+        .collect()
+}
+
+fn diff_bodies(
+    from: Option<PublicFetchRecordBody>,
+    to: Option<PublicFetchRecordBody>,
+) -> (BodyDiff, DiffSummary) {
+    match (from, to) {
+        (Some(PublicFetchRecordBody::Json(from)), Some(PublicFetchRecordBody::Json(to))) => {
+            let patch = json_patch::diff(&from, &to);
+            let mut summary = DiffSummary {
+                body_changed: !patch.0.is_empty(),
+                ..Default::default()
+            };
+            for op in &patch.0 {
+                match op {
+                    PatchOperation::Add(_) => summary.additions += 1,
+                    PatchOperation::Remove(_) => summary.removals += 1,
+                    PatchOperation::Replace(_)
+                    | PatchOperation::Move(_)
+                    | PatchOperation::Copy(_) => summary.modifications += 1,
+                    PatchOperation::Test(_) => {}
+                }
+            }
+            (BodyDiff::JsonPatch(patch), summary)
+        }
+        (from, to) => {
+            let from = body_as_text(from);
+            let to = body_as_text(to);
+            let text_diff = TextDiff::from_lines(&from, &to);
+            let mut summary = DiffSummary {
+                body_changed: from != to,
+                ..Default::default()
+            };
+            for change in text_diff.iter_all_changes() {
+                match change.tag() {
+                    ChangeTag::Insert => summary.additions += 1,
+                    ChangeTag::Delete => summary.removals += 1,
+                    ChangeTag::Equal => {}
+                }
+            }
+            let unified = text_diff
+                .unified_diff()
+                .context_radius(UNIFIED_CONTEXT_LINES)
+                .header("from", "to")
+                .to_string();
+            (BodyDiff::Unified(unified), summary)
+        }
+    }
+}
+
+fn body_as_text(body: Option<PublicFetchRecordBody>) -> String {
+    match body {
+        Some(PublicFetchRecordBody::PlainText(text)) => text,
+        Some(PublicFetchRecordBody::Json(value)) => {
+            serde_json::to_string_pretty(&value).unwrap_or_default()
+        }
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::Timestamp;
+    use serde_json::json;
+
+    use super::*;
+
+    fn record(id: i64, status: Status, body: Option<&str>) -> FetchRecord {
+        let fetched_at = Timestamp::new(id * 60, 0).unwrap();
+        FetchRecord {
+            id: Some(id),
+            config: 1,
+            fetched_at,
+            created_at: fetched_at,
+            source_url: "https://example.com".to_owned(),
+            status,
+            body_text: body.map(str::to_owned),
+            valid_json: body.map(|_| true),
+            latency_ms: None,
+            maintenance: false,
+            assertions: None,
+            manual: false,
+            from_db: true,
+        }
+    }
+
+    fn ids(changes: &[RecordDiff]) -> Vec<(Option<i64>, Option<i64>)> {
+        changes
+            .iter()
+            .map(|change| (change.from.id, change.to.id))
+            .collect()
+    }
+
+    #[test]
+    fn downtime_records_are_not_changes() {
+        let records = [
+            record(1, Status::HttpOk(200), Some(r#"{"a":1}"#)),
+            record(2, Status::Unknown, None),
+            record(3, Status::HttpOk(200), Some(r#"{"a":1}"#)),
+        ];
+        assert!(record_changes(&records).is_empty());
+    }
+
+    #[test]
+    fn changes_across_downtime_are_relative_to_the_record_before_it() {
+        let records = [
+            record(1, Status::HttpOk(200), Some(r#"{"a":1}"#)),
+            record(2, Status::Unknown, None),
+            record(3, Status::HttpOk(200), Some(r#"{"a":2}"#)),
+        ];
+        assert_eq!(ids(&record_changes(&records)), vec![(Some(1), Some(3))]);
+    }
+
+    #[test]
+    fn records_differing_only_in_flags_are_not_changes() {
+        let mut manual = record(2, Status::HttpOk(200), Some(r#"{"a":1}"#));
+        manual.manual = true;
+        let records = [
+            record(1, Status::HttpOk(200), Some(r#"{"a":1}"#)),
+            manual,
+            record(3, Status::HttpErr(500), Some(r#"{"a":1}"#)),
+        ];
+        let changes = record_changes(&records);
+        assert_eq!(ids(&changes), vec![(Some(2), Some(3))]);
+        assert!(changes[0].summary.status_changed);
+        assert!(!changes[0].summary.body_changed);
+    }
+
+    #[test]
+    fn json_summary_counts_patch_operations() {
+        let (diff, summary) = diff_bodies(
+            Some(PublicFetchRecordBody::Json(
+                json!({"a": 1, "b": 2, "c": [1]}),
+            )),
+            Some(PublicFetchRecordBody::Json(
+                json!({"a": 5, "c": [1, 2], "d": true}),
+            )),
+        );
+        assert!(matches!(diff, BodyDiff::JsonPatch(_)));
+        assert!(summary.body_changed);
+        assert_eq!(
+            (summary.additions, summary.removals, summary.modifications),
+            (2, 1, 1)
+        );
+    }
+
+    #[test]
+    fn identical_json_is_no_change() {
+        let (_, summary) = diff_bodies(
+            Some(PublicFetchRecordBody::Json(json!({"a": [1, 2]}))),
+            Some(PublicFetchRecordBody::Json(json!({"a": [1, 2]}))),
+        );
+        assert!(!summary.body_changed);
+        assert_eq!(
+            (summary.additions, summary.removals, summary.modifications),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn text_summary_counts_lines() {
+        let (diff, summary) = diff_bodies(
+            Some(PublicFetchRecordBody::PlainText(
+                "one\ntwo\nthree\n".to_owned(),
+            )),
+            Some(PublicFetchRecordBody::PlainText(
+                "one\n2\nthree\nfour\n".to_owned(),
+            )),
+        );
+        assert!(matches!(diff, BodyDiff::Unified(_)));
+        assert!(summary.body_changed);
+        assert_eq!(
+            (summary.additions, summary.removals, summary.modifications),
+            (2, 1, 0)
+        );
+    }
+
+    #[test]
+    fn a_missing_body_is_diffed_as_empty_text() {
+        let (_, summary) = diff_bodies(
+            None,
+            Some(PublicFetchRecordBody::PlainText("a\nb\n".to_owned())),
+        );
+        assert!(summary.body_changed);
+        assert_eq!((summary.additions, summary.removals), (2, 0));
+    }
+}
@@ -0,0 +1,124 @@
+use std::mem::discriminant;
+use std::sync::OnceLock;
+
+use jiff::{Timestamp, ToSpan};
+
+use crate::db::{get_flap_state, get_samples_since, save_flap_state, DBConn};
+use crate::models::{
+    config::{Config, ConfigHash},
+    fetch_result::Status,
+    flap::{FlapDetection, FlapState},
+    notification::{NotificationEvent, Trigger},
+};
+use crate::notify;
+
+static DETECTION: OnceLock<FlapDetection> = OnceLock::new();
+
+/// Sets up flap detection as defined in fetche.toml
+pub fn init(detection: FlapDetection) {
+    if let Err(e) = detection.validate() {
+        panic!("Invalid flap detection settings: {e}");
+    }
+    if DETECTION.set(detection).is_err() {
+        tracing::warn!("Flap detection was already initialized, ignoring");
+    }
+}
+
+/// Decides whether the config is flapping, should be called after each fetch is sampled
+/// - starting and stopping to flap is sent to the notification targets
+#[tracing::instrument(name = "flap_detection", skip_all)]
+pub async fn evaluate(
+    db: &DBConn,
+    config: &Config,
+    status: Status,
+    now: Timestamp,
+) -> Result<(), sqlx::Error> {
+    let Some(detection) = DETECTION.get().filter(|detection| detection.enabled) else {
+        return Ok(());
+    };
+    let samples = get_samples_since(db, config.hash, now - detection.window_s.seconds()).await?;
+    let changes = samples
+        .windows(2)
+        .filter(|pair| discriminant(&pair[0].status) != discriminant(&pair[1].status))
+        .count();
+
+    let previous = get_flap_state(db, config.hash).await?;
+    let was_flapping = previous.as_ref().is_some_and(|previous| previous.flapping);
+    let flapping = match was_flapping {
+        true => changes > detection.stable_threshold,
+        false => changes >= detection.threshold,
+    };
+    let changed = flapping != was_flapping;
+    let (since, suppressed) = match &previous {
+        Some(previous) if !changed => (previous.since, previous.suppressed),
+        _ => (now, 0),
+    };
+    save_flap_state(
+        db,
+        FlapState {
+            config: config.hash,
+            flapping,
+            since,
+            evaluated_at: now,
+            changes: changes as i64,
+            suppressed,
+        },
+    )
+    .await?;
+
+    if changed {
+        let (trigger, message) = match flapping {
+            true => (
+                Trigger::FlappingStarted,
+                format!(
+                    "{changes} status changes in the last {}s, holding back notifications until it settles down",
+                    detection.window_s
+                ),
+            ),
+            false => {
+                let previous = previous.expect("a config that stopped flapping has a state");
+                (
+                    Trigger::FlappingStopped,
+                    format!(
+                        "Stable again after flapping for {}s, {} notifications were held back",
+                        now.as_second() - previous.since.as_second(),
+                        previous.suppressed
+                    ),
+                )
+            }
+        };
+        notify::dispatch(
+            db,
+            vec![NotificationEvent {
+                trigger,
+                config: config.hash,
+                config_name: config.name.clone(),
+                source_url: config.source_url.clone(),
+                previous_status: status,
+                status,
+                fetched_at: now,
+                previous_record: None,
+                record: None,
+                alert: None,
+                message: Some(message),
+                latency_ms: None,
+                diff: None,
+            }],
+        );
+    }
+    Ok(())
+}
+
+/// Counts `count` notifications as held back if the config is flapping
+/// - returns whether they should be held back
+pub async fn suppress(db: &DBConn, config: ConfigHash, count: usize) -> Result<bool, sqlx::Error> {
+    let Some(mut state) = get_flap_state(db, config)
+        .await?
+        .filter(|state| state.flapping)
+    else {
+        return Ok(false);
+    };
+    state.suppressed += count as i64;
+    save_flap_state(db, state).await?;
+    Ok(true)
+}
@@ -0,0 +1,100 @@
+use jiff::Timestamp;
+
+use crate::db::{get_incidents, get_open_incident, save_incident, DBConn};
+use crate::models::{
+    config::ConfigHash,
+    incident::{Incident, IncidentSummary},
+    sample::FetchSample,
+};
+
+/// Opens, extends or resolves the incident of a config based on the outcome of a fetch
+/// - `error` explains why the source couldn't be reached, if that was the case
+/// - planned maintenance isn't an incident: an open incident ends when maintenance starts,
+///   and failures during maintenance are ignored
+#[tracing::instrument(name = "incident", skip_all)]
+pub async fn track(
+    db: &DBConn,
+    sample: &FetchSample,
+    error: Option<String>,
+    maintenance: bool,
+) -> Result<(), sqlx::Error> {
+    let open = get_open_incident(db, sample.config).await?;
+    if maintenance {
+        if let Some(mut incident) = open {
+            incident.ended_at = Some(sample.fetched_at);
+            save_incident(db, incident).await?;
+        }
+        return Ok(());
+    }
+    match (open, sample.status.is_failure()) {
+        (Some(mut incident), true) => {
+            incident.failed_checks += 1;
+            save_incident(db, incident).await?;
+        }
+        (Some(mut incident), false) => {
+            incident.ended_at = Some(sample.fetched_at);
+            save_incident(db, incident).await?;
+        }
+        (None, true) => {
+            save_incident(
+                db,
+                Incident {
+                    id: None,
+                    config: sample.config,
+                    started_at: sample.fetched_at,
+                    ended_at: None,
+                    duration_s: None,
+                    first_status: sample.status,
+                    first_error: error,
+                    failed_checks: 1,
+                },
+            )
+            .await?;
+        }
+        (None, false) => {}
+    }
+    Ok(())
+}
+
+/// Computes uptime and mean time to recovery of a config over [since, until)
+/// - time not covered by an incident counts as up
+pub async fn summarize(
+    db: &DBConn,
+    config: ConfigHash,
+    since: Timestamp,
+    until: Timestamp,
+) -> Result<IncidentSummary, sqlx::Error> {
+    let incidents: Vec<_> = get_incidents(db, Some(config), since)
+        .await?
+        .into_iter()
+        .filter(|incident| incident.started_at < until)
+        .collect();
+
+    let downtime_s: i64 = incidents
+        .iter()
+        .map(|incident| incident.overlap_s(since, until))
+        .sum();
+    let resolved: Vec<_> = incidents
+        .iter()
+        .filter_map(|incident| incident.duration_s)
+        .collect();
+    let mttr_s = match resolved.len() {
+        0 => None,
+        n => Some(resolved.iter().sum::<i64>() / n as i64),
+    };
+    let window_s = (until.as_second() - since.as_second()).max(1);
+
+    Ok(IncidentSummary {
+        config,
+        since,
+        until,
+        incidents: incidents.len(),
+        open_incidents: incidents
+            .iter()
+            .filter(|incident| incident.ended_at.is_none())
+            .count(),
+        downtime_s,
+        mttr_s,
+        uptime: 1.0 - (downtime_s as f64 / window_s as f64),
+    })
+}
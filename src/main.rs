@@ -1,21 +1,45 @@
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap};
+use std::time::{Duration, Instant};
 
-use api::run_server;
+use api::{run_server, ApiError};
+use clap::Parser;
+use cli::{Cli, Command};
 use db::{
-    connect, create_or_activate_config, deactivate_all_configs, get_active_configs, get_config,
-    record_downtime, record_fetch, record_fetch_config,
+    connect, create_or_activate_config, deactivate_file_configs, get_active_configs, get_config,
+    prune_samples, record_downtime, record_fetch, record_fetch_config, record_sample,
 };
-use jiff::Timestamp;
+use jiff::{Timestamp, ToSpan};
 use models::{
-    config::{Config, ConfigHash, FetcheConfig, RawConfigHash},
+    config::{Config, ConfigHash, ConfigSource, FetcheConfig, RawConfigHash},
     fetch_result::{FetchRecord, PublicFetchRecord, RawFetchRecord, Status},
+    sample::FetchSample,
 };
 use sqlx::SqlitePool;
 use tokio_cron_scheduler::{Job, JobScheduler};
+use tracing::Instrument;
 
+pub mod alert;
 pub mod api;
+pub mod assertion;
+pub mod auth;
+pub mod changes;
+pub mod cli;
 pub mod db;
+pub mod diff;
+pub mod flap;
+pub mod html;
+pub mod incident;
+pub mod maintenance;
+pub mod metrics;
 pub mod models;
+pub mod notify;
+pub mod probe;
+pub mod status_page;
+pub mod telemetry;
+pub mod uptime;
+
+/// How long the outcome of every single fetch is kept around for alert rules
+const SAMPLE_RETENTION_S: i64 = 7 * 24 * 60 * 60;
 
 #[derive(Debug, Default, serde::Deserialize)]
 pub struct Query {
@@ -29,34 +53,65 @@ pub struct Query {
 
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run(cli.config).await,
+        Command::Keys(command) => cli::keys(command).await,
+        Command::Probe { config, file } => cli::probe(&cli.config, config, file).await,
+    }
+}
+
+async fn run(config_path: String) {
+    let config_str = std::fs::read_to_string(&config_path).expect("Failed to read config, make sure ./fetche.toml exists or that FETCHE_CONFIG_PATH points to the right location");
+    let parsed_config: FetcheConfig =
+        toml::from_str(&config_str).expect("Failed to parse fetche.toml");
+    let tracer_provider = telemetry::init(&parsed_config.telemetry, &parsed_config.logging);
+    let server = parsed_config.server.with_env_overrides();
+    tracing::info!(path = %config_path, "Read config");
+
     let pool = connect().await;
     sqlx::migrate!()
         .run(&pool)
         .await
         .expect("Failed to run migrations");
 
-    let _ = run_query(
-        &pool,
-        Query {
-            decompress: true,
-            filter_config: None,
-        },
-    )
-    .await;
+    deactivate_file_configs(&pool).await.unwrap();
 
-    deactivate_all_configs(&pool).await.unwrap();
-
-    println!("Reading config...");
-    let config_path = std::env::var("FETCHE_CONFIG_PATH").unwrap_or("./fetche.toml".to_owned());
-    let config_str = std::fs::read_to_string(config_path).expect("Failed to read config, make sure ./fetche.toml exists or that FETCHE_CONFIG_PATH points to the right location");
-    let parsed_config: FetcheConfig =
-        toml::from_str(&config_str).expect("Failed to parse fetche.toml");
+    auth::init(parsed_config.auth);
+    notify::init(parsed_config.notifications);
+    alert::init(parsed_config.alerts);
+    flap::init(parsed_config.flapping);
+    status_page::init(parsed_config.status_page);
+    maintenance::load_windows(&pool, parsed_config.maintenance)
+        .await
+        .expect("Failed to load maintenance windows");
     let mut activation_handles = vec![];
     for input in parsed_config.configs {
+        if let Err(e) = input.validate() {
+            panic!("Invalid config {}: {e}", input.source_url);
+        }
         let client = pool.clone();
         let handle = tokio::spawn(async move {
             let config = Config::from(input);
-            println!("Activating config: {config:#?}");
+            tracing::info!(
+                config = %config.hash,
+                url = %config.source_url,
+                name = config.name.as_deref(),
+                "Activating config"
+            );
+            let existing = get_config(&client, config.hash)
+                .await
+                .expect("Failed to look up config");
+            // Taking it over would archive the api config once it's removed from fetche.toml
+            if existing
+                .is_some_and(|existing| existing.active && existing.source == ConfigSource::Api)
+            {
+                tracing::warn!(
+                    config = %config.hash,
+                    "The same config was created through the api, ignoring it in fetche.toml"
+                );
+                return;
+            }
             create_or_activate_config(&client, config.clone())
                 .await
                 .expect("Failed to activate config");
@@ -76,27 +131,35 @@ async fn main() {
         .add(
             Job::new_async("0/1 * * * * *", move |_uuid, _l| {
                 let pool = pool.clone();
-                Box::pin(async move {
-                    let configs = get_active_configs(&pool)
-                        .await
-                        .expect("Failed to fetch active configs");
-                    let mut tick_handles = vec![];
-                    for config in configs {
-                        let client = pool.clone();
-                        let handle = tokio::spawn(async move {
-                            return tick(&client, config).await.unwrap();
-                        });
-                        tick_handles.push(handle);
-                    }
-                    for handle in tick_handles {
-                        let _elapsed_opt = handle.await.expect("Panic in task");
-                        //if let Some(elapsed) = elapsed_opt {
-                        //    print!("{elapsed}");
-                        //}
-                        //print!("\t");
+                // Parent of the fetches started by this tick, and of the downtime writes
+                let span = tracing::info_span!("schedule");
+                Box::pin(
+                    async move {
+                        let configs = get_active_configs(&pool)
+                            .await
+                            .expect("Failed to fetch active configs");
+                        let mut tick_handles = vec![];
+                        for config in configs {
+                            let client = pool.clone();
+                            let handle = tokio::spawn(
+                                async move {
+                                    return tick(&client, config).await.unwrap();
+                                }
+                                .in_current_span(),
+                            );
+                            tick_handles.push(handle);
+                        }
+                        for handle in tick_handles {
+                            let _elapsed_opt = handle.await.expect("Panic in task");
+                            //if let Some(elapsed) = elapsed_opt {
+                            //    print!("{elapsed}");
+                            //}
+                            //print!("\t");
+                        }
+                        //println!();
                     }
-                    //println!();
-                })
+                    .instrument(span),
+                )
             })
             .expect("Failed to create job"),
         )
@@ -106,16 +169,24 @@ async fn main() {
     sched.shutdown_on_ctrl_c();
     sched.set_shutdown_handler(Box::new(|| {
         Box::pin(async move {
-            println!("Shut down done");
+            tracing::info!("Shut down done");
         })
     }));
 
     sched.start().await.expect("Failed to start scheduler");
-    run_server().await.unwrap();
+    run_server(server).await.unwrap();
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            tracing::error!(error = %e, "Failed to flush traces");
+        }
+    }
 }
 
 async fn tick(db: &SqlitePool, config_hash: ConfigHash) -> Result<Option<i64>, sqlx::Error> {
-    let config = get_config(db, config_hash).await?;
+    // Paused configs aren't scheduled, but are still ticked once on start
+    let config = get_config(db, config_hash)
+        .await?
+        .filter(|config| !config.paused);
     let mut since = None;
     if let Some(config) = config {
         let time_since_last_fetch = record_downtime(db, &config).await?;
@@ -129,66 +200,160 @@ async fn tick(db: &SqlitePool, config_hash: ConfigHash) -> Result<Option<i64>, s
         };
 
         if should_fetch {
-            let fetched_at = Timestamp::now();
-            let source_url = config.source_url.clone();
-            let fetch_result = reqwest::get(&source_url).await;
-
-            let created_at = Timestamp::now();
-
-            let result: FetchRecord = match fetch_result {
-                Ok(resp) => {
-                    let rstatus = resp.status();
-                    let statuscode = rstatus.as_u16();
-                    let status = if rstatus.is_success() {
-                        Status::HttpOk(statuscode)
-                    } else {
-                        Status::HttpErr(statuscode)
-                    };
-                    let body_text = resp.text().await.ok();
-                    let valid_json = match (config.try_parse_json, &body_text) {
-                        (true, Some(text)) => {
-                            Some(match serde_json::from_str::<serde_json::Value>(text) {
-                                Ok(_) => true,
-                                _ => false,
-                            })
-                        }
-                        _ => None,
-                    };
-                    FetchRecord {
-                        config: config_hash,
-                        fetched_at,
-                        created_at,
-                        source_url,
-                        body_text,
-                        valid_json,
-                        status,
-                        from_db: true,
-                    }
-                }
-                Err(_) => FetchRecord {
-                    config: config_hash,
-                    fetched_at,
-                    created_at,
-                    source_url,
-                    body_text: None,
-                    valid_json: None,
-                    status: Status::Error,
-                    from_db: true,
-                },
-            };
-            let _diff = record_fetch(db, result).await?;
-            //println!("[{config_hash}] Seconds since last update: {diff:?}");
-            record_fetch_config(db, config).await?;
+            fetch(db, config, false).await?;
         }
     }
 
-    return Ok(since);
+    Ok(since)
+}
+
+/// Fetches the source of a config and records the outcome
+/// - `manual` fetches were triggered through the api instead of by the schedule
+/// - the returned record has no id if it matched the latest one and wasn't stored
+#[tracing::instrument(
+    name = "fetch",
+    skip_all,
+    fields(config = %config.hash, url = %config.source_url, manual = manual, status, latency_ms)
+)]
+pub async fn fetch(
+    db: &SqlitePool,
+    config: Config,
+    manual: bool,
+) -> Result<FetchRecord, sqlx::Error> {
+    let config_hash = config.hash;
+    let _in_progress = metrics::FetchInProgress::start();
+    let fetched_at = Timestamp::now();
+    let in_maintenance = maintenance::is_active(db, &config, fetched_at).await?;
+    let (mut result, error) = request(&config, fetched_at).await;
+    result.maintenance = in_maintenance;
+    result.manual = manual;
+    let latency_ms = result.latency_ms.unwrap_or_default();
+    let status = result.status;
+    // config and url are fields of the fetch span
+    match &error {
+        Some(error) => tracing::warn!(
+            status = %status,
+            duration_ms = latency_ms,
+            error,
+            "Fetch failed"
+        ),
+        None => tracing::info!(
+            status = %status,
+            duration_ms = latency_ms,
+            "Fetched"
+        ),
+    }
+    let span = tracing::Span::current();
+    span.record("status", tracing::field::display(status));
+    span.record("latency_ms", latency_ms);
+    let sample = FetchSample {
+        config: config_hash,
+        fetched_at,
+        status,
+        latency_ms,
+        assertions: result.assertions.clone(),
+    };
+    metrics::observe_fetch(
+        config_hash,
+        status,
+        fetched_at,
+        Duration::from_millis(latency_ms as u64),
+    );
+    let write_started = Instant::now();
+    record_sample(db, sample.clone()).await?;
+    metrics::observe_db_write(write_started.elapsed());
+    prune_samples(db, config_hash, fetched_at - SAMPLE_RETENTION_S.seconds()).await?;
+    // Before recording, so that a config that just started flapping stays quiet
+    if !in_maintenance {
+        flap::evaluate(db, &config, status, fetched_at).await?;
+    }
+
+    let write_started = Instant::now();
+    let mut record = result.clone();
+    record.id = record_fetch(db, &config, result).await?;
+    metrics::observe_db_write(write_started.elapsed());
+    incident::track(db, &sample, error, in_maintenance).await?;
+    if !in_maintenance {
+        alert::evaluate(db, &config, status).await?;
+    }
+    record_fetch_config(db, config).await?;
+    Ok(record)
+}
+
+/// Requests the source of a config and checks the response, without storing anything
+/// - the error explains why the source couldn't be reached, if that was the case
+pub async fn request(config: &Config, fetched_at: Timestamp) -> (FetchRecord, Option<String>) {
+    let started = Instant::now();
+    let source_url = config.source_url.clone();
+    let fetch_result = reqwest::get(&source_url)
+        .instrument(tracing::info_span!("http_request", url = %source_url))
+        .await;
+
+    let created_at = Timestamp::now();
+
+    let mut error = None;
+    let result: FetchRecord = match fetch_result {
+        Ok(resp) => {
+            let statuscode = resp.status().as_u16();
+            let headers = resp.headers().clone();
+            let body_text = resp.text().await.ok();
+            let latency_ms = started.elapsed().as_millis() as i64;
+            let results = assertion::check(
+                &config.assertions,
+                &assertion::Response {
+                    status_code: statuscode,
+                    headers: &headers,
+                    body: body_text.as_deref(),
+                    latency_ms,
+                },
+            );
+            let status = assertion::status(statuscode, &results);
+            let valid_json = match (config.try_parse_json, &body_text) {
+                (true, Some(text)) => Some(serde_json::from_str::<serde_json::Value>(text).is_ok()),
+                _ => None,
+            };
+            FetchRecord {
+                id: None,
+                config: config.hash,
+                fetched_at,
+                created_at,
+                source_url,
+                body_text,
+                valid_json,
+                latency_ms: Some(latency_ms),
+                maintenance: false,
+                manual: false,
+                assertions: (!results.is_empty()).then_some(results),
+                status,
+                from_db: true,
+            }
+        }
+        Err(e) => {
+            error = Some(e.to_string());
+            FetchRecord {
+                id: None,
+                config: config.hash,
+                fetched_at,
+                created_at,
+                source_url,
+                body_text: None,
+                valid_json: None,
+                latency_ms: Some(started.elapsed().as_millis() as i64),
+                maintenance: false,
+                manual: false,
+                assertions: None,
+                status: Status::Error,
+                from_db: true,
+            }
+        }
+    };
+    (result, error)
 }
 
 async fn run_query(
     db: &SqlitePool,
     query: Query,
-) -> Result<HashMap<ConfigHash, Vec<PublicFetchRecord>>, sqlx::Error> {
+) -> Result<HashMap<ConfigHash, Vec<PublicFetchRecord>>, ApiError> {
     let records = match query.filter_config {
         Some(config) => {
             let db_config_hash = config as RawConfigHash;
@@ -204,9 +369,8 @@ async fn run_query(
             .fetch_all(db)
             .await?
             .into_iter()
-            .map(FetchRecord::try_from)
-            .map(|parse_r| parse_r.unwrap())
-            .collect::<Vec<_>>()
+            .map(parse_record)
+            .collect::<Result<Vec<_>, _>>()?
         }
         None => sqlx::query_as!(
             RawFetchRecord,
@@ -218,24 +382,23 @@ async fn run_query(
         .fetch_all(db)
         .await?
         .into_iter()
-        .map(FetchRecord::try_from)
-        .map(|parse_r| parse_r.unwrap())
-        .collect::<Vec<_>>(),
+        .map(parse_record)
+        .collect::<Result<Vec<_>, _>>()?,
     };
 
     let mut configs = HashMap::new();
     for r in &records {
-        if !configs.contains_key(&r.config) {
-            let config = get_config(db, r.config)
-                .await?
-                .expect("No such config - programmer error");
-            configs.insert(r.config, config);
+        if let Entry::Vacant(entry) = configs.entry(r.config) {
+            let config = get_config(db, r.config).await?.ok_or_else(|| {
+                tracing::error!(config = %r.config, "Records refer to a config that doesn't exist");
+                ApiError::Internal
+            })?;
+            entry.insert(config);
         }
     }
 
     let mut records_by_config = HashMap::new();
     for config in configs.values() {
-        println!("[{}]", config.hash);
         let mut config_records = vec![];
         let mut matching_records: Vec<_> = records
             .iter()
@@ -254,6 +417,7 @@ async fn run_query(
                 if config_last_fetched != last.created_at =>
             {
                 matching_records.push(FetchRecord {
+                    id: None,
                     config: last.config,
                     source_url: last.source_url.clone(),
                     status: last.status,
@@ -261,6 +425,10 @@ async fn run_query(
                     created_at: Timestamp::now(),
                     body_text: last.body_text.clone(),
                     valid_json: last.valid_json,
+                    latency_ms: last.latency_ms,
+                    maintenance: last.maintenance,
+                    manual: last.manual,
+                    assertions: last.assertions.clone(),
                     from_db: false,
                 });
             }
@@ -270,10 +438,6 @@ async fn run_query(
         let mut previous_record: Option<FetchRecord> = None;
         for record in matching_records {
             let fetched_at = record.fetched_at.as_second();
-            println!(
-                "\tevent at {fetched_at}: {:?} {}",
-                record.status, record.source_url
-            );
 
             if query.decompress {
                 if let Some(prev) = previous_record {
@@ -283,6 +447,7 @@ async fn run_query(
                         let new_at = fetched_at - diff + config.fetch_interval_s;
 
                         config_records.push(FetchRecord {
+                            id: None,
                             config: prev.config,
                             source_url: prev.source_url.clone(),
                             status: prev.status,
@@ -291,6 +456,10 @@ async fn run_query(
                             created_at: Timestamp::now(),
                             body_text: prev.body_text.clone(),
                             valid_json: prev.valid_json,
+                            latency_ms: prev.latency_ms,
+                            maintenance: prev.maintenance,
+                            manual: prev.manual,
+                            assertions: prev.assertions.clone(),
                         });
 
                         diff = fetched_at - new_at;
@@ -316,3 +485,11 @@ async fn run_query(
         })
         .collect())
 }
+
+fn parse_record(raw: RawFetchRecord) -> Result<FetchRecord, ApiError> {
+    let id = raw.id;
+    FetchRecord::try_from(raw).map_err(|_| {
+        tracing::error!(record = ?id, "Failed to parse a stored record");
+        ApiError::Internal
+    })
+}
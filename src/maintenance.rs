@@ -0,0 +1,29 @@
+use jiff::Timestamp;
+
+use crate::db::{
+    create_maintenance_window, delete_config_maintenance_windows, get_maintenance_windows, DBConn,
+};
+use crate::models::{config::Config, maintenance::MaintenanceWindow};
+
+/// Replaces the maintenance windows from a previous fetche.toml with the current ones
+/// - windows created through the api are left alone
+pub async fn load_windows(db: &DBConn, windows: Vec<MaintenanceWindow>) -> Result<(), sqlx::Error> {
+    delete_config_maintenance_windows(db).await?;
+    for mut window in windows {
+        if let Err(e) = window.schedule.validate() {
+            panic!("Invalid maintenance window {}: {e}", window.name);
+        }
+        window.id = None;
+        window.from_config = true;
+        create_maintenance_window(db, window).await?;
+    }
+    Ok(())
+}
+
+/// Checks whether any maintenance window covering the config is active at the given time
+pub async fn is_active(db: &DBConn, config: &Config, at: Timestamp) -> Result<bool, sqlx::Error> {
+    Ok(get_maintenance_windows(db)
+        .await?
+        .iter()
+        .any(|window| window.applies_to(config) && window.schedule.is_active_at(at)))
+}
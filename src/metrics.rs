@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use jiff::Timestamp;
+
+use crate::db::{get_all_configs, get_latest_record, DBConn};
+use crate::models::{
+    config::{Config, ConfigHash},
+    fetch_result::Status,
+};
+
+/// Upper bounds of the histogram buckets, in seconds
+const BUCKETS_S: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+static METRICS: OnceLock<Mutex<Metrics>> = OnceLock::new();
+/// Fetches that have started but not finished yet
+static FETCHES_IN_PROGRESS: AtomicI64 = AtomicI64::new(0);
+
+#[derive(Default)]
+struct Metrics {
+    configs: HashMap<ConfigHash, ConfigMetrics>,
+    db_writes: Histogram,
+}
+
+#[derive(Default)]
+struct ConfigMetrics {
+    last_status: Option<Status>,
+    last_fetched_at: Option<Timestamp>,
+    fetches: u64,
+    failures: u64,
+    body_changes: u64,
+    latency: Histogram,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Observations per bucket, not cumulative
+    counts: [u64; BUCKETS_S.len()],
+    sum_s: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(bucket) = BUCKETS_S.iter().position(|bound| seconds <= *bound) {
+            self.counts[bucket] += 1;
+        }
+        self.sum_s += seconds;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS_S.iter().zip(self.counts) {
+            cumulative += count;
+            writeln!(
+                out,
+                "{name}_bucket{{{labels}{separator}le=\"{bound}\"}} {cumulative}"
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            "{name}_bucket{{{labels}{separator}le=\"+Inf\"}} {}",
+            self.count
+        )
+        .unwrap();
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{labels}}}")
+        };
+        writeln!(out, "{name}_sum{labels} {}", self.sum_s).unwrap();
+        writeln!(out, "{name}_count{labels} {}", self.count).unwrap();
+    }
+}
+
+fn metrics() -> &'static Mutex<Metrics> {
+    METRICS.get_or_init(Default::default)
+}
+
+/// Keeps track of a fetch that is in progress until dropped
+pub struct FetchInProgress;
+
+impl FetchInProgress {
+    pub fn start() -> Self {
+        FETCHES_IN_PROGRESS.fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for FetchInProgress {
+    fn drop(&mut self) {
+        FETCHES_IN_PROGRESS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub fn observe_fetch(config: ConfigHash, status: Status, fetched_at: Timestamp, latency: Duration) {
+    let mut metrics = metrics().lock().unwrap();
+    let config = metrics.configs.entry(config).or_default();
+    config.last_status = Some(status);
+    config.last_fetched_at = Some(fetched_at);
+    config.fetches += 1;
+    if status.is_failure() {
+        config.failures += 1;
+    }
+    config.latency.observe(latency);
+}
+
+pub fn observe_body_change(config: ConfigHash) {
+    let mut metrics = metrics().lock().unwrap();
+    metrics.configs.entry(config).or_default().body_changes += 1;
+}
+
+pub fn observe_db_write(duration: Duration) {
+    metrics().lock().unwrap().db_writes.observe(duration);
+}
+
+/// Renders every metric in the Prometheus text format
+/// - counters and histograms count from the start of fetche
+pub async fn render(db: &DBConn) -> Result<String, sqlx::Error> {
+    let configs = get_all_configs(db).await?;
+    // Extracted values come from the latest stored body of each active config
+    let mut extracted = vec![];
+    for config in configs.iter().filter(|config| config.active) {
+        if config.extract.is_empty() {
+            continue;
+        }
+        let Some(body) = get_latest_record(db, config.hash)
+            .await?
+            .and_then(|record| record.body_text)
+        else {
+            continue;
+        };
+        for extract in &config.extract {
+            if let Some(value) = extract.read(&body) {
+                extracted.push((config, &extract.name, value));
+            }
+        }
+    }
+
+    let metrics = metrics().lock().unwrap();
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "fetche_active_configs",
+        "gauge",
+        "Configs defined in the current fetche.toml or created through the api",
+    );
+    let active = configs.iter().filter(|config| config.active).count();
+    writeln!(out, "fetche_active_configs {active}").unwrap();
+
+    header(
+        &mut out,
+        "fetche_fetches_in_progress",
+        "gauge",
+        "Fetches that have started but not finished",
+    );
+    writeln!(
+        out,
+        "fetche_fetches_in_progress {}",
+        FETCHES_IN_PROGRESS.load(Ordering::Relaxed)
+    )
+    .unwrap();
+
+    header(
+        &mut out,
+        "fetche_db_write_duration_seconds",
+        "histogram",
+        "Time spent on a single write of the outcome of a fetch",
+    );
+    metrics
+        .db_writes
+        .render(&mut out, "fetche_db_write_duration_seconds", "");
+
+    let observed: Vec<_> = configs
+        .iter()
+        .filter_map(|config| Some((config, metrics.configs.get(&config.hash)?)))
+        .collect();
+
+    header(
+        &mut out,
+        "fetche_config_up",
+        "gauge",
+        "Whether the latest fetch returned HttpOk",
+    );
+    for (config, observed) in &observed {
+        if let Some(status) = observed.last_status {
+            let up = matches!(status, Status::HttpOk(_)) as u8;
+            writeln!(out, "fetche_config_up{{{}}} {up}", labels(config)).unwrap();
+        }
+    }
+
+    header(
+        &mut out,
+        "fetche_config_last_status_code",
+        "gauge",
+        "Http status of the latest fetch, 0 if there was no response",
+    );
+    for (config, observed) in &observed {
+        let code = match observed.last_status {
+            Some(Status::HttpOk(code) | Status::HttpErr(code) | Status::AssertionFailed(code)) => {
+                code
+            }
+            Some(_) => 0,
+            None => continue,
+        };
+        writeln!(
+            out,
+            "fetche_config_last_status_code{{{}}} {code}",
+            labels(config)
+        )
+        .unwrap();
+    }
+
+    header(
+        &mut out,
+        "fetche_config_last_fetch_timestamp_seconds",
+        "gauge",
+        "When the latest fetch was made",
+    );
+    for (config, observed) in &observed {
+        if let Some(fetched_at) = observed.last_fetched_at {
+            writeln!(
+                out,
+                "fetche_config_last_fetch_timestamp_seconds{{{}}} {}",
+                labels(config),
+                fetched_at.as_second()
+            )
+            .unwrap();
+        }
+    }
+
+    header(
+        &mut out,
+        "fetche_config_fetches_total",
+        "counter",
+        "Fetches made",
+    );
+    for (config, observed) in &observed {
+        writeln!(
+            out,
+            "fetche_config_fetches_total{{{}}} {}",
+            labels(config),
+            observed.fetches
+        )
+        .unwrap();
+    }
+
+    header(
+        &mut out,
+        "fetche_config_failures_total",
+        "counter",
+        "Fetches that returned HttpErr, AssertionFailed or Error",
+    );
+    for (config, observed) in &observed {
+        writeln!(
+            out,
+            "fetche_config_failures_total{{{}}} {}",
+            labels(config),
+            observed.failures
+        )
+        .unwrap();
+    }
+
+    header(
+        &mut out,
+        "fetche_config_body_changes_total",
+        "counter",
+        "Fetches that returned a different body than before",
+    );
+    for (config, observed) in &observed {
+        writeln!(
+            out,
+            "fetche_config_body_changes_total{{{}}} {}",
+            labels(config),
+            observed.body_changes
+        )
+        .unwrap();
+    }
+
+    header(
+        &mut out,
+        "fetche_config_fetch_duration_seconds",
+        "histogram",
+        "Time from sending the request to receiving the whole body",
+    );
+    for (config, observed) in &observed {
+        observed.latency.render(
+            &mut out,
+            "fetche_config_fetch_duration_seconds",
+            &labels(config),
+        );
+    }
+
+    header(
+        &mut out,
+        "fetche_config_value",
+        "gauge",
+        "Numbers extracted from the latest json body",
+    );
+    for (config, name, value) in extracted {
+        writeln!(
+            out,
+            "fetche_config_value{{{},value=\"{}\"}} {value}",
+            labels(config),
+            escape_label(name)
+        )
+        .unwrap();
+    }
+
+    Ok(out)
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {name} {help}").unwrap();
+    writeln!(out, "# TYPE {name} {kind}").unwrap();
+}
+
+fn labels(config: &Config) -> String {
+    format!(
+        "config=\"{}\",name=\"{}\"",
+        config.hash,
+        escape_label(config.name.as_deref().unwrap_or(&config.source_url))
+    )
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,183 @@
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use super::config::{ConfigHash, RawConfigHash};
+use super::i64_as_string;
+use crate::db::{bool_to_sqlite, sqlite_to_bool, RawBoolean, RawTimestamp};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlertRule {
+    /// Identifies the rule in the alert state and in notifications
+    pub name: String,
+    /// Config names or hashes the rule applies to, all configs if empty
+    #[serde(default)]
+    pub configs: Vec<String>,
+    pub condition: AlertCondition,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// The latest `count` fetches all failed to return HttpOk
+    ConsecutiveFailures { count: u32 },
+    /// The given latency percentile over the last `window_s` seconds is above `above_ms`
+    Latency {
+        percentile: f64,
+        above_ms: i64,
+        window_s: i64,
+    },
+    /// A number in the latest json body is outside of the given bounds
+    JsonValue {
+        /// JSON pointer to the value, for example "/items/0/price"
+        pointer: String,
+        #[serde(default)]
+        below: Option<f64>,
+        #[serde(default)]
+        above: Option<f64>,
+    },
+    /// The body hasn't changed for `for_s` seconds
+    NoChange { for_s: i64 },
+}
+
+impl AlertRule {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.condition {
+            AlertCondition::ConsecutiveFailures { count } if *count < 1 => {
+                Err(format!("{}: count must be at least 1", self.name))
+            }
+            AlertCondition::Latency { percentile, .. }
+                if !(*percentile > 0.0 && *percentile <= 100.0) =>
+            {
+                Err(format!(
+                    "{}: percentile must be above 0 and at most 100",
+                    self.name
+                ))
+            }
+            AlertCondition::Latency { window_s, .. } if *window_s < 1 => {
+                Err(format!("{}: window_s must be at least 1", self.name))
+            }
+            AlertCondition::JsonValue {
+                below: None,
+                above: None,
+                ..
+            } => Err(format!("{}: below or above is required", self.name)),
+            AlertCondition::NoChange { for_s } if *for_s < 1 => {
+                Err(format!("{}: for_s must be at least 1", self.name))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, FromRow)]
+pub struct RawAlertState {
+    pub rule: String,
+    pub config: RawConfigHash,
+    pub firing: RawBoolean,
+    pub since: RawTimestamp,
+    pub evaluated_at: RawTimestamp,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlertState {
+    pub rule: String,
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    pub firing: bool,
+    /// When the alert started firing or got resolved
+    pub since: Timestamp,
+    pub evaluated_at: Timestamp,
+    /// Explains the latest evaluation
+    pub message: String,
+}
+
+impl From<AlertState> for RawAlertState {
+    fn from(val: AlertState) -> Self {
+        Self {
+            rule: val.rule,
+            config: val.config as RawConfigHash,
+            firing: bool_to_sqlite(val.firing),
+            since: val.since.as_second(),
+            evaluated_at: val.evaluated_at.as_second(),
+            message: val.message,
+        }
+    }
+}
+
+impl From<RawAlertState> for AlertState {
+    fn from(raw: RawAlertState) -> Self {
+        Self {
+            rule: raw.rule,
+            config: raw.config as ConfigHash,
+            firing: sqlite_to_bool(raw.firing),
+            since: Timestamp::new(raw.since, 0).unwrap(),
+            evaluated_at: Timestamp::new(raw.evaluated_at, 0).unwrap(),
+            message: raw.message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(condition: AlertCondition) -> AlertRule {
+        AlertRule {
+            name: "rule".to_owned(),
+            configs: vec![],
+            condition,
+        }
+    }
+
+    #[test]
+    fn rejects_conditions_that_cannot_be_evaluated() {
+        assert!(rule(AlertCondition::ConsecutiveFailures { count: 0 })
+            .validate()
+            .is_err());
+        assert!(rule(AlertCondition::ConsecutiveFailures { count: 1 })
+            .validate()
+            .is_ok());
+        for (percentile, valid) in [
+            (0.0, false),
+            (-5.0, false),
+            (100.5, false),
+            (f64::NAN, false),
+            (0.1, true),
+            (100.0, true),
+        ] {
+            let latency = rule(AlertCondition::Latency {
+                percentile,
+                above_ms: 500,
+                window_s: 60,
+            });
+            assert_eq!(latency.validate().is_ok(), valid, "percentile {percentile}");
+        }
+    }
+
+    #[test]
+    fn rejects_conditions_that_can_never_fire() {
+        let unbounded = rule(AlertCondition::JsonValue {
+            pointer: "/price".to_owned(),
+            below: None,
+            above: None,
+        });
+        assert!(unbounded.validate().is_err());
+        let bounded = rule(AlertCondition::JsonValue {
+            pointer: "/price".to_owned(),
+            below: Some(10.0),
+            above: None,
+        });
+        assert!(bounded.validate().is_ok());
+        assert!(rule(AlertCondition::NoChange { for_s: -60 })
+            .validate()
+            .is_err());
+        assert!(rule(AlertCondition::NoChange { for_s: 0 })
+            .validate()
+            .is_err());
+        assert!(rule(AlertCondition::NoChange { for_s: 60 })
+            .validate()
+            .is_ok());
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+/// A check a response has to pass on top of the http status
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Assertion {
+    /// The http status is one of these, replaces the default 2xx check
+    Status { expected: Vec<u16> },
+    /// The body contains the text
+    BodyContains { text: String },
+    /// The body matches the regular expression
+    BodyMatches { regex: String },
+    /// The value at the JSON pointer equals `equals` and/or is a number within [min, max]
+    JsonPointer {
+        /// For example "/items/0/price"
+        pointer: String,
+        #[serde(default)]
+        equals: Option<serde_json::Value>,
+        #[serde(default, deserialize_with = "optional_number")]
+        min: Option<f64>,
+        #[serde(default, deserialize_with = "optional_number")]
+        max: Option<f64>,
+    },
+    /// The response has the header, optionally with the given value
+    Header {
+        name: String,
+        #[serde(default)]
+        equals: Option<String>,
+    },
+    /// The response arrived within `ms` milliseconds
+    MaxLatency { ms: i64 },
+    /// The body is at most `bytes` long
+    MaxBodySize { bytes: usize },
+}
+
+impl Assertion {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::BodyMatches { regex } => regex::Regex::new(regex)
+                .map(|_| ())
+                .map_err(|e| format!("Invalid regex {regex}: {e}")),
+            Self::JsonPointer {
+                pointer,
+                min: Some(min),
+                max: Some(max),
+                ..
+            } if min > max => Err(format!("{pointer}: min must not be above max")),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The outcome of a single assertion for a single fetch
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct AssertionResult {
+    pub assertion: Assertion,
+    pub passed: bool,
+    /// What was found instead, for failed assertions
+    pub message: Option<String>,
+}
+
+// With arbitrary_precision, numbers inside internally tagged enums reach f64 fields
+// as serde_json's private number map, which only serde_json::Value understands
+fn optional_number<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        Some(value) => value
+            .as_f64()
+            .map(Some)
+            .ok_or_else(|| de::Error::custom(format!("expected a number, got {value}"))),
+        None => Ok(None),
+    }
+}
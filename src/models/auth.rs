@@ -0,0 +1,68 @@
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use crate::db::RawTimestamp;
+
+/// Api authentication, set under `[auth]` in fetche.toml
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AuthConfig {
+    /// Every api request needs a key when enabled
+    #[serde(default)]
+    pub enabled: bool,
+    /// Keys on top of the ones stored in the database through `fetche keys create`
+    #[serde(default)]
+    pub keys: Vec<ConfiguredApiKey>,
+    /// Lets feed readers, which can't send an api key header, load the feeds with `?token=...`
+    /// - it only gives access to the feeds
+    #[serde(default)]
+    pub feed_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfiguredApiKey {
+    pub name: String,
+    pub key: String,
+    #[serde(default)]
+    pub scope: ApiScope,
+}
+
+/// What a key is allowed to do
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiScope {
+    /// GET requests only
+    #[default]
+    Read,
+    /// Any request, including ones that change data
+    Admin,
+}
+
+#[derive(Deserialize, Serialize, FromRow)]
+pub struct RawApiKey {
+    pub id: i64,
+    pub name: String,
+    pub key_hash: String,
+    pub scope: String,
+    pub created_at: RawTimestamp,
+}
+
+/// A key stored in the database, only its hash is kept
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    pub scope: ApiScope,
+    pub created_at: Timestamp,
+}
+
+impl From<RawApiKey> for ApiKey {
+    fn from(raw: RawApiKey) -> Self {
+        Self {
+            id: raw.id,
+            name: raw.name,
+            scope: serde_json::from_str(&raw.scope).unwrap(),
+            created_at: Timestamp::new(raw.created_at, 0).unwrap(),
+        }
+    }
+}
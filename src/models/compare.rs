@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Decides when a fetched body counts as a new version
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareMode {
+    /// Bodies have to match byte-for-byte
+    #[default]
+    Exact,
+    /// Bodies that are valid json are compared as parsed values
+    /// - key order and whitespace are ignored
+    /// - falls back to `Exact` if either body isn't valid json
+    SemanticJson,
+}
+
+/// Optional normalization applied to json values before a semantic comparison
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub struct Canonicalize {
+    /// Treat all arrays as unordered, so that reordered elements are not a change
+    #[serde(default)]
+    pub unordered_arrays: bool,
+    /// When sorting arrays, order objects by the value of this key (for example "id")
+    /// - elements without the key are ordered by their serialized form
+    #[serde(default)]
+    pub array_key: Option<String>,
+}
+
+/// Compares two json documents according to the given canonicalization rules
+/// - object keys are always compared in sorted order
+pub fn json_equal(a: &str, b: &str, canonicalize: Option<&Canonicalize>) -> Option<bool> {
+    let mut a: Value = serde_json::from_str(a).ok()?;
+    let mut b: Value = serde_json::from_str(b).ok()?;
+    if let Some(canonicalize) = canonicalize {
+        canonicalize_value(&mut a, canonicalize);
+        canonicalize_value(&mut b, canonicalize);
+    }
+    Some(a == b)
+}
+
+pub fn canonicalize_value(value: &mut Value, rules: &Canonicalize) {
+    match value {
+        Value::Array(items) => {
+            for item in items.iter_mut() {
+                canonicalize_value(item, rules);
+            }
+            if rules.unordered_arrays {
+                items.sort_by_cached_key(|item| sort_key(item, rules.array_key.as_deref()));
+            }
+        }
+        Value::Object(map) => {
+            for item in map.values_mut() {
+                canonicalize_value(item, rules);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn sort_key(item: &Value, array_key: Option<&str>) -> (u8, String) {
+    match (array_key, item) {
+        (Some(key), Value::Object(map)) if map.contains_key(key) => (0, map[key].to_string()),
+        _ => (1, item.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn unordered(array_key: Option<&str>) -> Canonicalize {
+        Canonicalize {
+            unordered_arrays: true,
+            array_key: array_key.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn json_equal_ignores_key_order_and_whitespace() {
+        assert_eq!(
+            json_equal(r#"{"a":1,"b":[1,2]}"#, r#"{ "b": [1, 2], "a": 1 }"#, None),
+            Some(true)
+        );
+        assert_eq!(json_equal(r#"{"a":1}"#, r#"{"a":2}"#, None), Some(false));
+        assert_eq!(json_equal("[1,2]", "[2,1]", None), Some(false));
+    }
+
+    #[test]
+    fn json_equal_is_undecided_for_invalid_json() {
+        assert_eq!(json_equal("not json", r#"{"a":1}"#, None), None);
+        assert_eq!(json_equal(r#"{"a":1}"#, "{", None), None);
+    }
+
+    #[test]
+    fn unordered_arrays_are_sorted_at_every_depth() {
+        let rules = unordered(None);
+        assert_eq!(json_equal("[1,2,3]", "[3,1,2]", Some(&rules)), Some(true));
+        assert_eq!(
+            json_equal(r#"{"a":[[2,1],"x"]}"#, r#"{"a":["x",[1,2]]}"#, Some(&rules)),
+            Some(true)
+        );
+        assert_eq!(json_equal("[1,2]", "[1,2,2]", Some(&rules)), Some(false));
+    }
+
+    #[test]
+    fn array_key_orders_objects_by_that_key() {
+        let mut value = json!([
+            {"id": 3, "name": "c"},
+            "loose",
+            {"id": 1, "name": "a"},
+            {"name": "no id"},
+            {"id": 2, "name": "b"}
+        ]);
+        canonicalize_value(&mut value, &unordered(Some("id")));
+        assert_eq!(
+            value,
+            json!([
+                {"id": 1, "name": "a"},
+                {"id": 2, "name": "b"},
+                {"id": 3, "name": "c"},
+                "loose",
+                {"name": "no id"}
+            ])
+        );
+    }
+
+    #[test]
+    fn array_key_without_unordered_arrays_keeps_the_order() {
+        let rules = Canonicalize {
+            unordered_arrays: false,
+            array_key: Some("id".to_owned()),
+        };
+        let mut value = json!([{"id": 2}, {"id": 1}]);
+        canonicalize_value(&mut value, &rules);
+        assert_eq!(value, json!([{"id": 2}, {"id": 1}]));
+    }
+}
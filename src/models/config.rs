@@ -4,7 +4,18 @@ use jiff::Timestamp;
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
+use super::alert::AlertRule;
+use super::assertion::Assertion;
+use super::auth::AuthConfig;
+use super::compare::{Canonicalize, CompareMode};
+use super::extract::Extract;
+use super::flap::FlapDetection;
 use super::i64_as_string;
+use super::maintenance::MaintenanceWindow;
+use super::notification::NotificationTarget;
+use super::server::ServerConfig;
+use super::status_page::StatusPageConfig;
+use super::telemetry::{LoggingConfig, TelemetryConfig};
 use crate::db::{bool_to_sqlite, sqlite_to_bool, RawBoolean, RawTimestamp};
 
 pub type RawConfigHash = i64;
@@ -18,6 +29,15 @@ pub struct RawConfig {
     pub try_parse_json: RawBoolean,
     pub active: RawBoolean,
     pub last_fetched: Option<RawTimestamp>,
+    pub compare: String,
+    pub canonicalize: Option<String>,
+    pub name: Option<String>,
+    pub sla_target: Option<f64>,
+    pub tags: String,
+    pub assertions: String,
+    pub extract: String,
+    pub source: String,
+    pub paused: RawBoolean,
 }
 
 impl From<Config> for RawConfig {
@@ -29,6 +49,15 @@ impl From<Config> for RawConfig {
             try_parse_json: bool_to_sqlite(val.try_parse_json),
             active: bool_to_sqlite(val.active),
             last_fetched: val.last_fetched.map(Timestamp::as_second),
+            compare: serde_json::to_string(&val.compare).unwrap(),
+            canonicalize: val.canonicalize.map(|c| serde_json::to_string(&c).unwrap()),
+            name: val.name,
+            sla_target: val.sla_target,
+            tags: serde_json::to_string(&val.tags).unwrap(),
+            assertions: serde_json::to_string(&val.assertions).unwrap(),
+            extract: serde_json::to_string(&val.extract).unwrap(),
+            source: serde_json::to_string(&val.source).unwrap(),
+            paused: bool_to_sqlite(val.paused),
         }
     }
 }
@@ -41,6 +70,15 @@ impl From<RawConfig> for Config {
             try_parse_json: sqlite_to_bool(raw.try_parse_json),
             active: sqlite_to_bool(raw.active),
             last_fetched: raw.last_fetched.map(|s| Timestamp::new(s, 0).unwrap()),
+            compare: serde_json::from_str(&raw.compare).unwrap(),
+            canonicalize: raw.canonicalize.map(|c| serde_json::from_str(&c).unwrap()),
+            name: raw.name,
+            sla_target: raw.sla_target,
+            tags: serde_json::from_str(&raw.tags).unwrap(),
+            assertions: serde_json::from_str(&raw.assertions).unwrap(),
+            extract: serde_json::from_str(&raw.extract).unwrap(),
+            source: serde_json::from_str(&raw.source).unwrap(),
+            paused: sqlite_to_bool(raw.paused),
         }
     }
 }
@@ -52,8 +90,38 @@ pub struct Config {
     pub source_url: String,
     pub fetch_interval_s: i64,
     pub try_parse_json: bool,
+    /// Whether the config is in the current fetche.toml, or hasn't been archived
+    /// if it was created through the api
     pub active: bool,
     pub last_fetched: Option<Timestamp>,
+    pub compare: CompareMode,
+    pub canonicalize: Option<Canonicalize>,
+    pub name: Option<String>,
+    pub sla_target: Option<f64>,
+    pub tags: Vec<String>,
+    pub assertions: Vec<Assertion>,
+    pub extract: Vec<Extract>,
+    pub source: ConfigSource,
+    /// Paused configs aren't fetched until they are resumed
+    pub paused: bool,
+}
+
+/// Where a config was defined, which decides who may change it
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    /// fetche.toml, configs missing from it are deactivated on start
+    #[default]
+    File,
+    /// The config api, kept until archived through the api
+    Api,
+}
+
+impl Config {
+    /// Checks if the config is referred to by a name or hash in fetche.toml
+    pub fn is_selected_by(&self, selector: &str) -> bool {
+        self.name.as_deref() == Some(selector) || self.hash.to_string() == selector
+    }
 }
 
 impl From<ConfigInput> for Config {
@@ -68,18 +136,98 @@ impl From<ConfigInput> for Config {
             try_parse_json: inp.try_parse_json,
             last_fetched: None,
             active: true,
+            compare: inp.compare,
+            canonicalize: inp.canonicalize,
+            name: inp.name,
+            sla_target: inp.sla_target,
+            tags: inp.tags,
+            assertions: inp.assertions,
+            extract: inp.extract,
+            source: ConfigSource::File,
+            paused: false,
         }
     }
 }
 
-#[derive(Deserialize, Serialize, Hash)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct ConfigInput {
     pub source_url: String,
     pub fetch_interval_s: i64,
     pub try_parse_json: bool,
+    #[serde(default)]
+    pub compare: CompareMode,
+    #[serde(default)]
+    pub canonicalize: Option<Canonicalize>,
+    /// Human readable name used in notifications, not part of the hash
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Availability the config should reach in percent, for example 99.9
+    /// - not part of the hash
+    #[serde(default)]
+    pub sla_target: Option<f64>,
+    /// Used to group configs, for example in maintenance windows, not part of the hash
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Checks responses have to pass to count as HttpOk, not part of the hash
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    /// Numbers read from the latest json body and exported on /metrics, not part of the hash
+    #[serde(default)]
+    pub extract: Vec<Extract>,
+}
+
+impl ConfigInput {
+    pub fn validate(&self) -> Result<(), String> {
+        let url = reqwest::Url::parse(&self.source_url)
+            .map_err(|e| format!("Invalid source_url: {e}"))?;
+        if !["http", "https"].contains(&url.scheme()) {
+            return Err("source_url must be an http or https url".to_owned());
+        }
+        if self.fetch_interval_s < 1 {
+            return Err("fetch_interval_s must be at least 1".to_owned());
+        }
+        for assertion in &self.assertions {
+            assertion.validate()?;
+        }
+        Ok(())
+    }
+}
+
+// Fields added after the initial release only contribute to the hash
+// when they differ from their defaults, so that existing configs keep their hash
+impl Hash for ConfigInput {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.source_url.hash(state);
+        self.fetch_interval_s.hash(state);
+        self.try_parse_json.hash(state);
+        if self.compare != CompareMode::default() {
+            self.compare.hash(state);
+        }
+        if let Some(canonicalize) = &self.canonicalize {
+            canonicalize.hash(state);
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct FetcheConfig {
     pub configs: Vec<ConfigInput>,
+    #[serde(default)]
+    pub notifications: Vec<NotificationTarget>,
+    #[serde(default)]
+    pub alerts: Vec<AlertRule>,
+    #[serde(default)]
+    pub flapping: FlapDetection,
+    #[serde(default)]
+    pub maintenance: Vec<MaintenanceWindow>,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub status_page: StatusPageConfig,
 }
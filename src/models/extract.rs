@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+/// A number read from the latest json body of a config, exported as a metric
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Extract {
+    /// Used as the `value` label
+    pub name: String,
+    /// JSON pointer to the value, for example "/items/0/price"
+    pub pointer: String,
+}
+
+impl Extract {
+    /// None if the body isn't json or the value isn't a number
+    /// - booleans count as 0 and 1
+    pub fn read(&self, body: &str) -> Option<f64> {
+        let json: serde_json::Value = serde_json::from_str(body).ok()?;
+        let value = json.pointer(&self.pointer)?;
+        match value.as_bool() {
+            Some(flag) => Some(f64::from(u8::from(flag))),
+            None => value.as_f64(),
+        }
+    }
+}
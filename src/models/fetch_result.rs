@@ -1,9 +1,13 @@
+use std::fmt;
+
 use jiff::Timestamp;
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
-use crate::db::RawTimestamp;
+use crate::db::{bool_to_sqlite, sqlite_to_bool, RawBoolean, RawTimestamp};
 
+use super::assertion::AssertionResult;
+use super::compare::{json_equal, Canonicalize, CompareMode};
 use super::config::{ConfigHash, RawConfigHash};
 use super::i64_as_string;
 
@@ -14,14 +18,39 @@ pub enum Status {
     HttpOk(u16),
     /// The source returned a non-ok http status
     HttpErr(u16),
+    /// The source returned an ok http status, but the response failed an assertion of the config
+    AssertionFailed(u16),
     /// No response, probably because fetche or the service was disconnected
     Error,
     /// Fetche was not up at this time - no request was made
     Unknown,
 }
 
+impl Status {
+    /// fetche reached (or tried to reach) the source, and it didn't respond as expected
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            Status::HttpErr(_) | Status::AssertionFailed(_) | Status::Error
+        )
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::HttpOk(code) => write!(f, "HttpOk({code})"),
+            Status::HttpErr(code) => write!(f, "HttpErr({code})"),
+            Status::AssertionFailed(code) => write!(f, "AssertionFailed({code})"),
+            Status::Error => write!(f, "Error"),
+            Status::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, FromRow)]
 pub struct RawFetchRecord {
+    pub id: Option<i64>,
     pub config: RawConfigHash,
     pub fetched_at: RawTimestamp,
     pub created_at: RawTimestamp,
@@ -29,12 +58,17 @@ pub struct RawFetchRecord {
     pub status: String,
     pub body_text: Option<String>,
     pub valid_json: Option<bool>,
+    pub latency_ms: Option<i64>,
+    pub maintenance: RawBoolean,
+    pub assertions: Option<String>,
+    pub manual: RawBoolean,
 }
 
 impl From<FetchRecord> for RawFetchRecord {
     fn from(val: FetchRecord) -> Self {
         let status = serde_json::to_string(&val.status).unwrap();
         Self {
+            id: val.id,
             config: val.config as RawConfigHash,
             fetched_at: val.fetched_at.as_second(),
             created_at: val.created_at.as_second(),
@@ -42,6 +76,12 @@ impl From<FetchRecord> for RawFetchRecord {
             status,
             body_text: val.body_text,
             valid_json: val.valid_json,
+            latency_ms: val.latency_ms,
+            maintenance: bool_to_sqlite(val.maintenance),
+            assertions: val
+                .assertions
+                .map(|results| serde_json::to_string(&results).unwrap()),
+            manual: bool_to_sqlite(val.manual),
         }
     }
 }
@@ -52,6 +92,7 @@ impl TryFrom<RawFetchRecord> for FetchRecord {
         let status: Status = serde_json::from_str(&raw.status).map_err(|_| ())?;
 
         Ok(Self {
+            id: raw.id,
             config: raw.config as ConfigHash,
             fetched_at: Timestamp::new(raw.fetched_at, 0).map_err(|_| ())?,
             created_at: Timestamp::new(raw.created_at, 0).map_err(|_| ())?,
@@ -59,6 +100,13 @@ impl TryFrom<RawFetchRecord> for FetchRecord {
             status,
             body_text: raw.body_text,
             valid_json: raw.valid_json,
+            latency_ms: raw.latency_ms,
+            maintenance: sqlite_to_bool(raw.maintenance),
+            assertions: match raw.assertions {
+                Some(results) => Some(serde_json::from_str(&results).map_err(|_| ())?),
+                None => None,
+            },
+            manual: sqlite_to_bool(raw.manual),
             from_db: true,
         })
     }
@@ -66,6 +114,8 @@ impl TryFrom<RawFetchRecord> for FetchRecord {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FetchRecord {
+    /// None for records that haven't been stored yet or were generated by decompression
+    pub id: Option<i64>,
     #[serde(with = "i64_as_string")]
     pub config: ConfigHash,
     pub fetched_at: Timestamp,
@@ -74,18 +124,69 @@ pub struct FetchRecord {
     pub status: Status,
     pub body_text: Option<String>,
     pub valid_json: Option<bool>,
+    /// Time it took to fetch the record, not part of the comparison
+    pub latency_ms: Option<i64>,
+    /// Fetched during a maintenance window
+    /// - for stored records, this describes the fetch that stored the record
+    pub maintenance: bool,
+    /// Results of the config's assertions, None if it has none or there was no response
+    /// - not part of the comparison, the status reflects them
+    pub assertions: Option<Vec<AssertionResult>>,
+    /// Triggered through the api instead of by the schedule
+    /// - for stored records, this describes the fetch that stored the record
+    pub manual: bool,
     /// actual record or a result of decompression?
     pub from_db: bool,
 }
 
 impl PartialEq for FetchRecord {
     fn eq(&self, other: &Self) -> bool {
-        return self.config == other.config
+        self.config == other.config
             && self.source_url == other.source_url
             && self.status == other.status
             && self.body_text == other.body_text
             && self.valid_json == other.valid_json
-            && self.from_db == other.from_db;
+            && self.maintenance == other.maintenance
+            && self.manual == other.manual
+            && self.from_db == other.from_db
+    }
+}
+
+impl FetchRecord {
+    /// Checks whether two records describe the same state of the source,
+    /// comparing bodies with the given mode instead of byte-for-byte
+    /// - maintenance and manual describe the fetch rather than the source, so they are ignored
+    pub fn matches(
+        &self,
+        other: &Self,
+        compare: CompareMode,
+        canonicalize: Option<&Canonicalize>,
+    ) -> bool {
+        self.config == other.config
+            && self.source_url == other.source_url
+            && self.status == other.status
+            && self.body_matches(other, compare, canonicalize)
+            && self.valid_json == other.valid_json
+            && self.from_db == other.from_db
+    }
+
+    /// Checks whether two records have the same body, according to the given mode
+    pub fn body_matches(
+        &self,
+        other: &Self,
+        compare: CompareMode,
+        canonicalize: Option<&Canonicalize>,
+    ) -> bool {
+        match (compare, self.valid_json, other.valid_json) {
+            (CompareMode::SemanticJson, Some(true), Some(true)) => {
+                match (&self.body_text, &other.body_text) {
+                    (Some(a), Some(b)) => json_equal(a, b, canonicalize)
+                        .unwrap_or_else(|| self.body_text == other.body_text),
+                    _ => self.body_text == other.body_text,
+                }
+            }
+            _ => self.body_text == other.body_text,
+        }
     }
 }
 
@@ -100,12 +201,17 @@ impl From<FetchRecord> for PublicFetchRecord {
             _ => None,
         };
         PublicFetchRecord {
+            id: val.id,
             config: val.config,
             fetched_at: val.fetched_at,
             created_at: val.created_at,
             source_url: val.source_url,
             status: val.status,
             data,
+            latency_ms: val.latency_ms,
+            maintenance: val.maintenance,
+            assertions: val.assertions,
+            manual: val.manual,
             from_db: val.from_db,
         }
     }
@@ -113,6 +219,7 @@ impl From<FetchRecord> for PublicFetchRecord {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PublicFetchRecord {
+    pub id: Option<i64>,
     #[serde(with = "i64_as_string")]
     pub config: ConfigHash,
     pub fetched_at: Timestamp,
@@ -120,6 +227,10 @@ pub struct PublicFetchRecord {
     pub source_url: String,
     pub status: Status,
     pub data: Option<PublicFetchRecordBody>,
+    pub latency_ms: Option<i64>,
+    pub maintenance: bool,
+    pub assertions: Option<Vec<AssertionResult>>,
+    pub manual: bool,
     /// actual record or a result of decompression?
     pub from_db: bool,
 }
@@ -131,3 +242,43 @@ pub enum PublicFetchRecordBody {
     #[serde(rename = "json")]
     Json(serde_json::Value),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(body: &str) -> FetchRecord {
+        FetchRecord {
+            id: None,
+            config: 1,
+            fetched_at: Timestamp::UNIX_EPOCH,
+            created_at: Timestamp::UNIX_EPOCH,
+            source_url: "https://example.com".to_owned(),
+            status: Status::HttpOk(200),
+            body_text: Some(body.to_owned()),
+            valid_json: Some(true),
+            latency_ms: None,
+            maintenance: false,
+            assertions: None,
+            manual: false,
+            from_db: true,
+        }
+    }
+
+    #[test]
+    fn fetch_flags_dont_make_a_record_different() {
+        let stored = record(r#"{"a":1}"#);
+        let mut fetched = record(r#"{"a":1}"#);
+        fetched.manual = true;
+        fetched.maintenance = true;
+        assert!(stored.matches(&fetched, CompareMode::Exact, None));
+    }
+
+    #[test]
+    fn bodies_are_compared_with_the_configured_mode() {
+        let stored = record(r#"{"a":1,"b":2}"#);
+        let fetched = record(r#"{"b":2,"a":1}"#);
+        assert!(!stored.matches(&fetched, CompareMode::Exact, None));
+        assert!(stored.matches(&fetched, CompareMode::SemanticJson, None));
+    }
+}
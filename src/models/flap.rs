@@ -0,0 +1,138 @@
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use super::config::{ConfigHash, RawConfigHash};
+use super::i64_as_string;
+use crate::db::{bool_to_sqlite, sqlite_to_bool, RawBoolean, RawTimestamp};
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_window_s() -> i64 {
+    10 * 60
+}
+
+fn default_threshold() -> usize {
+    5
+}
+
+fn default_stable_threshold() -> usize {
+    2
+}
+
+/// When a config is considered to be flapping, set under `[flapping]` in fetche.toml
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FlapDetection {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// How far back status changes are counted
+    #[serde(default = "default_window_s")]
+    pub window_s: i64,
+    /// A config starts flapping once this many status changes happened within the window
+    #[serde(default = "default_threshold")]
+    pub threshold: usize,
+    /// A flapping config is stable again once at most this many status changes remain
+    /// within the window
+    #[serde(default = "default_stable_threshold")]
+    pub stable_threshold: usize,
+}
+
+impl Default for FlapDetection {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            window_s: default_window_s(),
+            threshold: default_threshold(),
+            stable_threshold: default_stable_threshold(),
+        }
+    }
+}
+
+impl FlapDetection {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_s < 1 {
+            return Err("window_s must be at least 1".to_owned());
+        }
+        if self.threshold < 1 {
+            return Err("threshold must be at least 1".to_owned());
+        }
+        if self.stable_threshold >= self.threshold {
+            return Err("stable_threshold must be lower than threshold".to_owned());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, FromRow)]
+pub struct RawFlapState {
+    pub config: RawConfigHash,
+    pub flapping: RawBoolean,
+    pub since: RawTimestamp,
+    pub evaluated_at: RawTimestamp,
+    pub changes: i64,
+    pub suppressed: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FlapState {
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    pub flapping: bool,
+    /// When the config started flapping or became stable
+    pub since: Timestamp,
+    pub evaluated_at: Timestamp,
+    /// Status changes within the detection window at the latest evaluation
+    pub changes: i64,
+    /// Status and body change notifications held back since the config started flapping
+    pub suppressed: i64,
+}
+
+impl From<FlapState> for RawFlapState {
+    fn from(val: FlapState) -> Self {
+        Self {
+            config: val.config as RawConfigHash,
+            flapping: bool_to_sqlite(val.flapping),
+            since: val.since.as_second(),
+            evaluated_at: val.evaluated_at.as_second(),
+            changes: val.changes,
+            suppressed: val.suppressed,
+        }
+    }
+}
+
+impl From<RawFlapState> for FlapState {
+    fn from(raw: RawFlapState) -> Self {
+        Self {
+            config: raw.config as ConfigHash,
+            flapping: sqlite_to_bool(raw.flapping),
+            since: Timestamp::new(raw.since, 0).unwrap(),
+            evaluated_at: Timestamp::new(raw.evaluated_at, 0).unwrap(),
+            changes: raw.changes,
+            suppressed: raw.suppressed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_must_leave_room_to_settle_down() {
+        assert!(FlapDetection::default().validate().is_ok());
+        let equal = FlapDetection {
+            stable_threshold: 5,
+            threshold: 5,
+            ..Default::default()
+        };
+        assert!(equal.validate().is_err());
+        let zero = FlapDetection {
+            stable_threshold: 0,
+            threshold: 0,
+            ..Default::default()
+        };
+        assert!(zero.validate().is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use super::config::{ConfigHash, RawConfigHash};
+use super::fetch_result::Status;
+use super::i64_as_string;
+use crate::db::RawTimestamp;
+
+#[derive(Deserialize, Serialize, FromRow)]
+pub struct RawIncident {
+    pub id: Option<i64>,
+    pub config: RawConfigHash,
+    pub started_at: RawTimestamp,
+    pub ended_at: Option<RawTimestamp>,
+    pub first_status: String,
+    pub first_error: Option<String>,
+    pub failed_checks: i64,
+}
+
+/// A period during which every fetch of a config failed
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Incident {
+    pub id: Option<i64>,
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    pub started_at: Timestamp,
+    /// None while the incident is still open
+    pub ended_at: Option<Timestamp>,
+    pub duration_s: Option<i64>,
+    /// Status of the fetch that opened the incident
+    pub first_status: Status,
+    /// Why the first fetch failed, if fetche couldn't reach the source
+    pub first_error: Option<String>,
+    pub failed_checks: i64,
+}
+
+impl Incident {
+    /// Seconds the incident overlaps with [since, until), open incidents last until now
+    pub fn overlap_s(&self, since: Timestamp, until: Timestamp) -> i64 {
+        let start = self.started_at.max(since);
+        let end = self.ended_at.unwrap_or_else(Timestamp::now).min(until);
+        (end.as_second() - start.as_second()).max(0)
+    }
+}
+
+impl From<Incident> for RawIncident {
+    fn from(val: Incident) -> Self {
+        Self {
+            id: val.id,
+            config: val.config as RawConfigHash,
+            started_at: val.started_at.as_second(),
+            ended_at: val.ended_at.map(Timestamp::as_second),
+            first_status: serde_json::to_string(&val.first_status).unwrap(),
+            first_error: val.first_error,
+            failed_checks: val.failed_checks,
+        }
+    }
+}
+
+impl From<RawIncident> for Incident {
+    fn from(raw: RawIncident) -> Self {
+        Self {
+            id: raw.id,
+            config: raw.config as ConfigHash,
+            started_at: Timestamp::new(raw.started_at, 0).unwrap(),
+            ended_at: raw.ended_at.map(|s| Timestamp::new(s, 0).unwrap()),
+            duration_s: raw.ended_at.map(|ended_at| ended_at - raw.started_at),
+            first_status: serde_json::from_str(&raw.first_status).unwrap(),
+            first_error: raw.first_error,
+            failed_checks: raw.failed_checks,
+        }
+    }
+}
+
+/// Reliability figures of a config over a time window, computed from its incidents
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IncidentSummary {
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    pub since: Timestamp,
+    pub until: Timestamp,
+    pub incidents: usize,
+    pub open_incidents: usize,
+    pub downtime_s: i64,
+    /// Mean time to recovery of the resolved incidents, None if there were none
+    pub mttr_s: Option<i64>,
+    /// Share of the window not covered by incidents, between 0 and 1
+    pub uptime: f64,
+}
@@ -0,0 +1,239 @@
+use chrono::DateTime;
+use croner::Cron;
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use super::config::Config;
+use crate::db::{bool_to_sqlite, sqlite_to_bool, RawBoolean};
+
+/// When a maintenance window is active
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaintenanceSchedule {
+    /// Active once, from `starts_at` until `ends_at`
+    Once {
+        starts_at: Timestamp,
+        ends_at: Timestamp,
+    },
+    /// Active for `duration_s` seconds every time the cron expression matches, in UTC
+    /// - the expression may include seconds, for example "0 0 2 * * Sat"
+    Recurring { cron: String, duration_s: i64 },
+}
+
+impl MaintenanceSchedule {
+    /// Checks that the schedule can ever be active
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Once { starts_at, ends_at } if ends_at <= starts_at => {
+                Err("ends_at must be after starts_at".to_owned())
+            }
+            Self::Once { .. } => Ok(()),
+            Self::Recurring { duration_s, .. } if *duration_s <= 0 => {
+                Err("duration_s must be positive".to_owned())
+            }
+            Self::Recurring { cron, .. } => parse_cron(cron).map(|_| ()),
+        }
+    }
+
+    pub fn is_active_at(&self, at: Timestamp) -> bool {
+        match self {
+            Self::Once { starts_at, ends_at } => *starts_at <= at && at < *ends_at,
+            Self::Recurring { cron, duration_s } => {
+                let Ok(cron) = parse_cron(cron) else {
+                    return false;
+                };
+                // Active if the latest start is less than duration_s ago
+                let Some(window_start) =
+                    DateTime::from_timestamp(at.as_second() - duration_s + 1, 0)
+                else {
+                    return false;
+                };
+                cron.find_next_occurrence(&window_start, true)
+                    .is_ok_and(|start| start.timestamp() <= at.as_second())
+            }
+        }
+    }
+
+    /// The periods within [since, until) during which the schedule is active, oldest first
+    /// - overlapping occurrences of a recurring schedule are merged
+    pub fn active_ranges(&self, since: Timestamp, until: Timestamp) -> Vec<(Timestamp, Timestamp)> {
+        let ranges = match self {
+            Self::Once { starts_at, ends_at } => vec![(*starts_at, *ends_at)],
+            Self::Recurring { cron, duration_s } => {
+                let Ok(cron) = parse_cron(cron) else {
+                    return vec![];
+                };
+                let mut ranges: Vec<(i64, i64)> = vec![];
+                // Occurrences that started up to duration_s ago are still active at `since`
+                let mut search_from = since.as_second() - duration_s + 1;
+                while let Some(start) = DateTime::from_timestamp(search_from, 0)
+                    .and_then(|from| cron.find_next_occurrence(&from, true).ok())
+                    .map(|start| start.timestamp())
+                    .filter(|start| *start < until.as_second())
+                {
+                    let end = start + duration_s;
+                    match ranges.last_mut() {
+                        Some(last) if start <= last.1 => last.1 = end,
+                        _ => ranges.push((start, end)),
+                    }
+                    search_from = start + 1;
+                }
+                ranges
+                    .into_iter()
+                    .map(|(start, end)| {
+                        (
+                            Timestamp::new(start, 0).unwrap(),
+                            Timestamp::new(end, 0).unwrap_or(Timestamp::MAX),
+                        )
+                    })
+                    .collect()
+            }
+        };
+        ranges
+            .into_iter()
+            .map(|(start, end)| (start.max(since), end.min(until)))
+            .filter(|(start, end)| start < end)
+            .collect()
+    }
+}
+
+fn parse_cron(expression: &str) -> Result<Cron, String> {
+    Cron::new(expression)
+        .with_seconds_optional()
+        .parse()
+        .map_err(|e| format!("Invalid cron expression {expression}: {e}"))
+}
+
+#[derive(Deserialize, Serialize, FromRow)]
+pub struct RawMaintenanceWindow {
+    pub id: Option<i64>,
+    pub name: String,
+    pub configs: String,
+    pub tags: String,
+    pub schedule: String,
+    pub from_config: RawBoolean,
+}
+
+/// A period during which alerts and notifications are held back
+/// and fetched records are left out of uptime reports
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MaintenanceWindow {
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub name: String,
+    /// Config names or hashes the window applies to
+    #[serde(default)]
+    pub configs: Vec<String>,
+    /// Config tags the window applies to
+    /// - the window applies to all configs if both configs and tags are empty
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(flatten)]
+    pub schedule: MaintenanceSchedule,
+    /// Windows from fetche.toml are replaced on every start, the ones created
+    /// through the api are kept
+    #[serde(default)]
+    pub from_config: bool,
+}
+
+impl MaintenanceWindow {
+    pub fn applies_to(&self, config: &Config) -> bool {
+        (self.configs.is_empty() && self.tags.is_empty())
+            || self.configs.iter().any(|s| config.is_selected_by(s))
+            || self.tags.iter().any(|tag| config.tags.contains(tag))
+    }
+}
+
+impl From<MaintenanceWindow> for RawMaintenanceWindow {
+    fn from(val: MaintenanceWindow) -> Self {
+        Self {
+            id: val.id,
+            name: val.name,
+            configs: serde_json::to_string(&val.configs).unwrap(),
+            tags: serde_json::to_string(&val.tags).unwrap(),
+            schedule: serde_json::to_string(&val.schedule).unwrap(),
+            from_config: bool_to_sqlite(val.from_config),
+        }
+    }
+}
+
+impl From<RawMaintenanceWindow> for MaintenanceWindow {
+    fn from(raw: RawMaintenanceWindow) -> Self {
+        Self {
+            id: raw.id,
+            name: raw.name,
+            configs: serde_json::from_str(&raw.configs).unwrap(),
+            tags: serde_json::from_str(&raw.tags).unwrap(),
+            schedule: serde_json::from_str(&raw.schedule).unwrap(),
+            from_config: sqlite_to_bool(raw.from_config),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(second: i64) -> Timestamp {
+        Timestamp::new(second, 0).unwrap()
+    }
+
+    fn nightly() -> MaintenanceSchedule {
+        // Every day from 02:00 to 02:30 UTC
+        MaintenanceSchedule::Recurring {
+            cron: "0 0 2 * * *".to_owned(),
+            duration_s: 30 * 60,
+        }
+    }
+
+    #[test]
+    fn recurring_schedules_are_active_for_their_duration() {
+        let schedule = nightly();
+        let two_am = 2 * 60 * 60;
+        assert!(!schedule.is_active_at(at(two_am - 1)));
+        assert!(schedule.is_active_at(at(two_am)));
+        assert!(schedule.is_active_at(at(two_am + 30 * 60 - 1)));
+        assert!(!schedule.is_active_at(at(two_am + 30 * 60)));
+        // The next day
+        assert!(schedule.is_active_at(at(86400 + two_am + 60)));
+    }
+
+    #[test]
+    fn recurring_ranges_include_occurrences_that_started_before_since() {
+        let two_am = 2 * 60 * 60;
+        let ranges = nightly().active_ranges(at(two_am + 600), at(2 * 86400));
+        assert_eq!(
+            ranges,
+            vec![
+                (at(two_am + 600), at(two_am + 1800)),
+                (at(86400 + two_am), at(86400 + two_am + 1800)),
+            ]
+        );
+    }
+
+    #[test]
+    fn overlapping_occurrences_are_merged() {
+        let schedule = MaintenanceSchedule::Recurring {
+            cron: "0 */10 * * * *".to_owned(),
+            duration_s: 15 * 60,
+        };
+        assert_eq!(
+            schedule.active_ranges(at(0), at(1800)),
+            vec![(at(0), at(1800))]
+        );
+    }
+
+    #[test]
+    fn one_off_ranges_are_clipped() {
+        let schedule = MaintenanceSchedule::Once {
+            starts_at: at(100),
+            ends_at: at(200),
+        };
+        assert_eq!(
+            schedule.active_ranges(at(150), at(1000)),
+            vec![(at(150), at(200))]
+        );
+        assert!(schedule.active_ranges(at(200), at(1000)).is_empty());
+    }
+}
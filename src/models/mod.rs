@@ -1,5 +1,21 @@
+pub mod alert;
+pub mod assertion;
+pub mod auth;
+pub mod compare;
 pub mod config;
+pub mod extract;
 pub mod fetch_result;
+pub mod flap;
+pub mod incident;
+pub mod maintenance;
+pub mod notification;
+pub mod probe;
+pub mod sample;
+pub mod server;
+pub mod status;
+pub mod status_page;
+pub mod telemetry;
+pub mod uptime;
 
 // From https://github.com/serde-rs/json/issues/329
 pub mod i64_as_string {
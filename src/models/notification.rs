@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use super::config::{ConfigHash, RawConfigHash};
+use super::fetch_result::Status;
+use super::i64_as_string;
+use crate::db::{bool_to_sqlite, sqlite_to_bool, RawBoolean, RawTimestamp};
+
+/// What a notification target wants to hear about
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    /// The config moved between HttpOk, HttpErr and Error
+    StatusChange,
+    /// The source returned a different body than before
+    BodyChange,
+    /// An alert rule started firing
+    AlertFiring,
+    /// A firing alert rule got resolved
+    AlertResolved,
+    /// The status changed too often, status and body changes are held back from now on
+    FlappingStarted,
+    /// The status settled down again
+    FlappingStopped,
+}
+
+fn default_triggers() -> Vec<Trigger> {
+    vec![
+        Trigger::StatusChange,
+        Trigger::BodyChange,
+        Trigger::AlertFiring,
+        Trigger::AlertResolved,
+        Trigger::FlappingStarted,
+        Trigger::FlappingStopped,
+    ]
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NotificationTarget {
+    /// Identifies the target in the delivery log
+    pub name: String,
+    #[serde(default = "default_triggers")]
+    pub on: Vec<Trigger>,
+    /// How many times a failed delivery is retried
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    #[serde(flatten)]
+    pub kind: NotificationKind,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationKind {
+    Webhook(WebhookTarget),
+    Email(EmailTarget),
+}
+
+fn default_method() -> String {
+    "POST".to_owned()
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebhookTarget {
+    /// May contain `{{variable}}` placeholders, for example for unique ids
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Payload layout, ignored if a template is given
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Json body with `{{variable}}` placeholders, the event itself is sent if missing
+    /// - substituted values are escaped for use inside json strings
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+/// Built in payload layouts for chat services
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// The event itself as json
+    #[default]
+    Json,
+    /// Slack incoming webhook, also understood by Mattermost and Rocket.Chat
+    Slack,
+    /// Discord webhook with an embed
+    Discord,
+    /// Matrix m.room.message event, for the client-server api send endpoint
+    Matrix,
+    /// Plain text body with title and tags headers for ntfy
+    Ntfy,
+}
+
+/// How the connection to the smtp server is secured
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Plain text, only meant for local smtp servers
+    None,
+    /// Upgrade a plain connection with STARTTLS, port 587 by default
+    #[default]
+    Starttls,
+    /// Implicit TLS, port 465 by default
+    Tls,
+}
+
+fn default_subject() -> String {
+    "[fetche] {{config_name}}: {{trigger}}".to_owned()
+}
+
+fn default_body() -> String {
+    "{{config_name}} ({{source_url}})\n\
+     Trigger: {{trigger}} {{alert}}\n\
+     Status: {{previous_status}} -> {{status}}\n\
+     {{message}}\n\
+     At: {{fetched_at}}\n"
+        .to_owned()
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EmailTarget {
+    pub host: String,
+    /// Defaults to the standard port of the chosen security
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    /// Plain text with `{{variable}}` placeholders, same as webhook templates
+    #[serde(default = "default_subject")]
+    pub subject: String,
+    #[serde(default = "default_body")]
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NotificationEvent {
+    pub trigger: Trigger,
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    pub config_name: Option<String>,
+    pub source_url: String,
+    pub previous_status: Status,
+    pub status: Status,
+    pub fetched_at: Timestamp,
+    pub previous_record: Option<i64>,
+    pub record: Option<i64>,
+    /// Name of the alert rule, for alert triggers
+    pub alert: Option<String>,
+    /// Explanation of the alert, for alert triggers
+    pub message: Option<String>,
+    /// How long the fetch took, for status and body changes
+    pub latency_ms: Option<i64>,
+    /// Truncated diff against the previous record, for status and body changes
+    pub diff: Option<String>,
+}
+
+impl NotificationEvent {
+    /// Values available to notification templates
+    pub fn template_vars(&self) -> Vec<(&'static str, String)> {
+        let trigger = serde_json::to_value(self.trigger).unwrap();
+        vec![
+            ("trigger", trigger.as_str().unwrap_or_default().to_owned()),
+            ("config", self.config.to_string()),
+            (
+                "config_name",
+                self.config_name
+                    .clone()
+                    .unwrap_or_else(|| self.source_url.clone()),
+            ),
+            ("source_url", self.source_url.clone()),
+            ("previous_status", self.previous_status.to_string()),
+            ("status", self.status.to_string()),
+            ("fetched_at", self.fetched_at.to_string()),
+            (
+                "previous_record",
+                self.previous_record
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+            ),
+            (
+                "record",
+                self.record.map(|id| id.to_string()).unwrap_or_default(),
+            ),
+            ("alert", self.alert.clone().unwrap_or_default()),
+            ("message", self.message.clone().unwrap_or_default()),
+            (
+                "latency_ms",
+                self.latency_ms.map(|ms| ms.to_string()).unwrap_or_default(),
+            ),
+            ("diff", self.diff.clone().unwrap_or_default()),
+        ]
+    }
+}
+
+#[derive(Deserialize, Serialize, FromRow)]
+pub struct RawDelivery {
+    pub id: Option<i64>,
+    pub target: String,
+    pub config: RawConfigHash,
+    pub record: Option<i64>,
+    pub trigger: String,
+    pub attempt: i64,
+    pub status_code: Option<i64>,
+    pub error: Option<String>,
+    pub delivered: RawBoolean,
+    pub created_at: RawTimestamp,
+}
+
+/// A single attempt at delivering a notification
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Delivery {
+    pub id: Option<i64>,
+    pub target: String,
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    pub record: Option<i64>,
+    pub trigger: Trigger,
+    pub attempt: i64,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub delivered: bool,
+    pub created_at: Timestamp,
+}
+
+impl From<Delivery> for RawDelivery {
+    fn from(val: Delivery) -> Self {
+        Self {
+            id: val.id,
+            target: val.target,
+            config: val.config as RawConfigHash,
+            record: val.record,
+            trigger: serde_json::to_string(&val.trigger).unwrap(),
+            attempt: val.attempt,
+            status_code: val.status_code.map(i64::from),
+            error: val.error,
+            delivered: bool_to_sqlite(val.delivered),
+            created_at: val.created_at.as_second(),
+        }
+    }
+}
+
+impl From<RawDelivery> for Delivery {
+    fn from(raw: RawDelivery) -> Self {
+        Self {
+            id: raw.id,
+            target: raw.target,
+            config: raw.config as ConfigHash,
+            record: raw.record,
+            trigger: serde_json::from_str(&raw.trigger).unwrap(),
+            attempt: raw.attempt,
+            status_code: raw.status_code.map(|code| code as u16),
+            error: raw.error,
+            delivered: sqlite_to_bool(raw.delivered),
+            created_at: Timestamp::new(raw.created_at, 0).unwrap(),
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use super::config::ConfigHash;
+use super::fetch_result::PublicFetchRecord;
+use super::i64_as_string;
+
+/// What fetche would record for a config, without storing anything
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Probe {
+    /// The hash the config would get
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    pub record: PublicFetchRecord,
+    /// Why the source couldn't be reached, if the status is Error
+    pub error: Option<String>,
+    /// Whether the body parsed as json, None unless try_parse_json is set
+    pub valid_json: Option<bool>,
+    /// The values listed under `extract`, None where nothing could be read
+    pub extracted: Vec<ExtractedValue>,
+    /// The body as it is compared with the next fetch, with `canonicalize` applied
+    /// - only set if compare is semantic_json and the body is json
+    pub compared_body: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExtractedValue {
+    pub name: String,
+    pub pointer: String,
+    pub value: Option<f64>,
+}
@@ -0,0 +1,59 @@
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use super::assertion::AssertionResult;
+use super::config::{ConfigHash, RawConfigHash};
+use super::fetch_result::Status;
+use super::i64_as_string;
+use crate::db::RawTimestamp;
+
+#[derive(Deserialize, Serialize, FromRow)]
+pub struct RawFetchSample {
+    pub config: RawConfigHash,
+    pub fetched_at: RawTimestamp,
+    pub status: String,
+    pub latency_ms: i64,
+    pub assertions: Option<String>,
+}
+
+/// Outcome of every single fetch, unlike fetch_result which only stores changes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FetchSample {
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    pub fetched_at: Timestamp,
+    pub status: Status,
+    /// Time from sending the request to receiving the whole body
+    pub latency_ms: i64,
+    /// Pass or fail of every assertion of the config
+    pub assertions: Option<Vec<AssertionResult>>,
+}
+
+impl From<FetchSample> for RawFetchSample {
+    fn from(val: FetchSample) -> Self {
+        Self {
+            config: val.config as RawConfigHash,
+            fetched_at: val.fetched_at.as_second(),
+            status: serde_json::to_string(&val.status).unwrap(),
+            latency_ms: val.latency_ms,
+            assertions: val
+                .assertions
+                .map(|results| serde_json::to_string(&results).unwrap()),
+        }
+    }
+}
+
+impl From<RawFetchSample> for FetchSample {
+    fn from(raw: RawFetchSample) -> Self {
+        Self {
+            config: raw.config as ConfigHash,
+            fetched_at: Timestamp::new(raw.fetched_at, 0).unwrap(),
+            status: serde_json::from_str(&raw.status).unwrap(),
+            latency_ms: raw.latency_ms,
+            assertions: raw
+                .assertions
+                .map(|results| serde_json::from_str(&results).unwrap()),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+fn default_bind() -> Vec<String> {
+    vec!["0.0.0.0".to_owned()]
+}
+
+fn default_port() -> u16 {
+    8080
+}
+
+/// Where and how the api is served, set under `[server]` in fetche.toml
+/// - every field can be overridden with a FETCHE_* environment variable, see `with_env_overrides`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ServerConfig {
+    /// Addresses to listen on, such as "127.0.0.1" or "::"
+    /// - "unix:/path/to/fetche.sock" listens on a unix domain socket instead
+    #[serde(default = "default_bind")]
+    pub bind: Vec<String>,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Prefix of every route, such as "/fetche" when served behind a reverse proxy
+    #[serde(default)]
+    pub base_path: String,
+    /// Number of worker threads, one per cpu core by default
+    #[serde(default)]
+    pub workers: Option<usize>,
+    /// Origins allowed to call the api from a browser, none by default, "*" allows any
+    #[serde(default)]
+    pub cors_origins: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: default_bind(),
+            port: default_port(),
+            base_path: String::new(),
+            workers: None,
+            cors_origins: vec![],
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Applies FETCHE_BIND, FETCHE_PORT, FETCHE_BASE_PATH, FETCHE_WORKERS and
+    /// FETCHE_CORS_ORIGINS, lists are comma separated
+    pub fn with_env_overrides(mut self) -> Self {
+        let var = |name| std::env::var(name).ok();
+        let list = |value: String| value.split(',').map(|s| s.trim().to_owned()).collect();
+        if let Some(bind) = var("FETCHE_BIND") {
+            self.bind = list(bind);
+        }
+        if let Some(port) = var("FETCHE_PORT") {
+            self.port = port.parse().expect("FETCHE_PORT is not a valid port");
+        }
+        if let Some(base_path) = var("FETCHE_BASE_PATH") {
+            self.base_path = base_path;
+        }
+        if let Some(workers) = var("FETCHE_WORKERS") {
+            self.workers = Some(workers.parse().expect("FETCHE_WORKERS is not a number"));
+        }
+        if let Some(origins) = var("FETCHE_CORS_ORIGINS") {
+            self.cors_origins = list(origins);
+        }
+        self
+    }
+
+    /// The base path with a leading slash and without a trailing one, empty for the root
+    pub fn normalized_base_path(&self) -> String {
+        let trimmed = self.base_path.trim_matches('/');
+        if trimmed.is_empty() {
+            String::new()
+        } else {
+            format!("/{trimmed}")
+        }
+    }
+}
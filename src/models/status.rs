@@ -0,0 +1,246 @@
+use std::mem::discriminant;
+
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use super::config::{ConfigHash, RawConfigHash};
+use super::fetch_result::Status;
+use super::i64_as_string;
+use crate::db::RawTimestamp;
+
+#[derive(Deserialize, Serialize, FromRow)]
+pub struct RawLatestState {
+    pub config: RawConfigHash,
+    pub record: Option<i64>,
+    pub status: String,
+    pub fetched_at: RawTimestamp,
+    pub latency_ms: Option<i64>,
+    pub changed_at: RawTimestamp,
+    pub last_success_at: Option<RawTimestamp>,
+    pub streak: i64,
+    pub body_changed_at: RawTimestamp,
+}
+
+/// The outcome of a config's latest fetch, kept up to date on every fetch
+/// - unlike the records, fetches that matched the previous one are counted as well
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LatestState {
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    /// The stored record matching the latest fetch
+    pub record: Option<i64>,
+    pub status: Status,
+    pub fetched_at: Timestamp,
+    pub latency_ms: Option<i64>,
+    /// When the status or the body last changed
+    pub changed_at: Timestamp,
+    /// When the source last answered with an ok http status
+    pub last_success_at: Option<Timestamp>,
+    /// Consecutive fetches with the same kind of status, including the latest one
+    pub streak: i64,
+    /// When the body last changed, compared with the config's compare mode
+    pub body_changed_at: Timestamp,
+}
+
+/// What differs between a fetch and the previous observed one
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Changes {
+    pub status: bool,
+    pub body: bool,
+}
+
+impl LatestState {
+    /// The state after a fetch with the given outcome
+    /// - `changes` tells what differs from the previous observed fetch
+    pub fn after_fetch(
+        previous: Option<&LatestState>,
+        config: ConfigHash,
+        record: Option<i64>,
+        status: Status,
+        fetched_at: Timestamp,
+        latency_ms: Option<i64>,
+        changes: Changes,
+    ) -> Self {
+        Self {
+            config,
+            record,
+            status,
+            fetched_at,
+            latency_ms,
+            changed_at: match previous {
+                Some(previous) if !changes.status && !changes.body => previous.changed_at,
+                _ => fetched_at,
+            },
+            body_changed_at: match previous {
+                Some(previous) if !changes.body => previous.body_changed_at,
+                _ => fetched_at,
+            },
+            last_success_at: match status {
+                Status::HttpOk(_) => Some(fetched_at),
+                _ => previous.and_then(|previous| previous.last_success_at),
+            },
+            streak: match previous {
+                Some(previous) if discriminant(&previous.status) == discriminant(&status) => {
+                    previous.streak + 1
+                }
+                _ => 1,
+            },
+        }
+    }
+}
+
+impl From<LatestState> for RawLatestState {
+    fn from(val: LatestState) -> Self {
+        Self {
+            config: val.config as RawConfigHash,
+            record: val.record,
+            status: serde_json::to_string(&val.status).unwrap(),
+            fetched_at: val.fetched_at.as_second(),
+            latency_ms: val.latency_ms,
+            changed_at: val.changed_at.as_second(),
+            last_success_at: val.last_success_at.map(|at| at.as_second()),
+            streak: val.streak,
+            body_changed_at: val.body_changed_at.as_second(),
+        }
+    }
+}
+
+impl From<RawLatestState> for LatestState {
+    fn from(raw: RawLatestState) -> Self {
+        Self {
+            config: raw.config as ConfigHash,
+            record: raw.record,
+            status: serde_json::from_str(&raw.status).unwrap(),
+            fetched_at: Timestamp::new(raw.fetched_at, 0).unwrap(),
+            latency_ms: raw.latency_ms,
+            changed_at: Timestamp::new(raw.changed_at, 0).unwrap(),
+            last_success_at: raw.last_success_at.map(|at| Timestamp::new(at, 0).unwrap()),
+            streak: raw.streak,
+            body_changed_at: Timestamp::new(raw.body_changed_at, 0).unwrap(),
+        }
+    }
+}
+
+/// An active config and the outcome of its latest fetch, as returned by `/status`
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigStatus {
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    pub name: Option<String>,
+    pub source_url: String,
+    pub paused: bool,
+    /// None until the config was fetched for the first time
+    pub latest: Option<LatestState>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(second: i64) -> Timestamp {
+        Timestamp::new(second, 0).unwrap()
+    }
+
+    #[test]
+    fn unchanged_fetches_keep_the_change_time_and_extend_the_streak() {
+        let first = LatestState::after_fetch(
+            None,
+            1,
+            Some(1),
+            Status::HttpOk(200),
+            at(10),
+            None,
+            Changes {
+                status: true,
+                body: true,
+            },
+        );
+        let second = LatestState::after_fetch(
+            Some(&first),
+            1,
+            Some(1),
+            Status::HttpOk(200),
+            at(20),
+            None,
+            Changes::default(),
+        );
+        assert_eq!(second.changed_at, at(10));
+        assert_eq!(second.last_success_at, Some(at(20)));
+        assert_eq!(second.streak, 2);
+    }
+
+    #[test]
+    fn a_different_kind_of_status_restarts_the_streak() {
+        let ok = LatestState::after_fetch(
+            None,
+            1,
+            Some(1),
+            Status::HttpOk(200),
+            at(10),
+            None,
+            Changes {
+                status: true,
+                body: true,
+            },
+        );
+        let failed = LatestState::after_fetch(
+            Some(&ok),
+            1,
+            Some(2),
+            Status::HttpErr(500),
+            at(20),
+            None,
+            Changes {
+                status: true,
+                body: true,
+            },
+        );
+        assert_eq!(failed.changed_at, at(20));
+        assert_eq!(failed.last_success_at, Some(at(10)));
+        assert_eq!(failed.streak, 1);
+        let still_failing = LatestState::after_fetch(
+            Some(&failed),
+            1,
+            Some(3),
+            Status::HttpErr(502),
+            at(30),
+            None,
+            Changes {
+                status: true,
+                body: true,
+            },
+        );
+        assert_eq!(still_failing.streak, 2);
+    }
+
+    #[test]
+    fn status_changes_keep_the_body_change_time() {
+        let first = LatestState::after_fetch(
+            None,
+            1,
+            Some(1),
+            Status::HttpOk(200),
+            at(10),
+            None,
+            Changes {
+                status: true,
+                body: true,
+            },
+        );
+        let failed = LatestState::after_fetch(
+            Some(&first),
+            1,
+            Some(2),
+            Status::HttpErr(500),
+            at(20),
+            None,
+            Changes {
+                status: true,
+                body: false,
+            },
+        );
+        assert_eq!(failed.changed_at, at(20));
+        assert_eq!(failed.body_changed_at, at(10));
+    }
+}
@@ -0,0 +1,155 @@
+use jiff::{civil::Date, Timestamp};
+use serde::{Deserialize, Serialize};
+
+fn default_title() -> String {
+    "Status".to_owned()
+}
+
+fn default_history_days() -> i64 {
+    90
+}
+
+/// A public status page for a subset of the configs, set under `[status_page]` in fetche.toml
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StatusPageConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_title")]
+    pub title: String,
+    /// How many days of uptime and incidents are shown
+    #[serde(default = "default_history_days")]
+    pub history_days: i64,
+    #[serde(default)]
+    pub groups: Vec<StatusPageGroup>,
+}
+
+impl Default for StatusPageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            title: default_title(),
+            history_days: default_history_days(),
+            groups: vec![],
+        }
+    }
+}
+
+/// Longest history the page can show
+pub const MAX_HISTORY_DAYS: i64 = 365;
+
+impl StatusPageConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_HISTORY_DAYS).contains(&self.history_days) {
+            return Err(format!(
+                "history_days must be between 1 and {MAX_HISTORY_DAYS}"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StatusPageGroup {
+    pub name: String,
+    pub components: Vec<StatusPageComponent>,
+}
+
+/// A config as it is shown on the status page
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StatusPageComponent {
+    /// Config name or hash
+    pub config: String,
+    /// Shown instead of anything that identifies the config
+    pub display_name: String,
+}
+
+/// What the public is told about a component, worst last
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum PublicState {
+    Operational,
+    /// Not fetched recently, paused, or never fetched at all
+    Unknown,
+    Maintenance,
+    /// Reachable, but the response failed an assertion
+    Degraded,
+    Down,
+}
+
+impl PublicState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PublicState::Operational => "Operational",
+            PublicState::Unknown => "Unknown",
+            PublicState::Maintenance => "Under maintenance",
+            PublicState::Degraded => "Degraded",
+            PublicState::Down => "Down",
+        }
+    }
+}
+
+/// The rendered status page, nothing in here identifies a config or its source
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusPage {
+    pub title: String,
+    pub generated_at: Timestamp,
+    /// The worst state of all components
+    pub state: PublicState,
+    pub groups: Vec<PublicGroup>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicGroup {
+    pub name: String,
+    pub state: PublicState,
+    pub components: Vec<PublicComponent>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicComponent {
+    pub name: String,
+    pub state: PublicState,
+    /// Over the whole history shown on the page
+    pub availability_percent: Option<f64>,
+    /// One entry per UTC day, oldest first
+    pub days: Vec<PublicDay>,
+    /// Newest first
+    pub incidents: Vec<PublicIncident>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicDay {
+    pub date: Date,
+    /// None if there is no data for the day
+    pub availability_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicIncident {
+    pub started_at: Timestamp,
+    /// None while the incident is still open
+    pub ended_at: Option<Timestamp>,
+    pub duration_s: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_history(history_days: i64) -> StatusPageConfig {
+        StatusPageConfig {
+            history_days,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn history_must_be_at_least_a_day_and_at_most_a_year() {
+        assert!(StatusPageConfig::default().validate().is_ok());
+        assert!(with_history(1).validate().is_ok());
+        assert!(with_history(MAX_HISTORY_DAYS).validate().is_ok());
+        assert!(with_history(0).validate().is_err());
+        assert!(with_history(-5).validate().is_err());
+        assert!(with_history(i64::MAX).validate().is_err());
+    }
+}
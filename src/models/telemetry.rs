@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+fn default_service_name() -> String {
+    "fetche".to_owned()
+}
+
+/// Where traces are sent, set under `[telemetry]` in fetche.toml
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TelemetryConfig {
+    /// Base url of an OTLP/HTTP collector, for example "http://localhost:4318"
+    /// - traces are only exported if this is set
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    /// Reported as service.name
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: default_service_name(),
+        }
+    }
+}
+
+fn default_filter() -> String {
+    "info".to_owned()
+}
+
+/// How fetche logs, set under `[logging]` in fetche.toml
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoggingConfig {
+    /// Which events are logged, in the `RUST_LOG` syntax such as "info,fetche=debug"
+    /// - `RUST_LOG` takes precedence if it is set
+    #[serde(default = "default_filter")]
+    pub filter: String,
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Logs are appended to this file instead of written to stdout
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            filter: default_filter(),
+            format: LogFormat::default(),
+            file: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// One line per event
+    #[default]
+    Text,
+    /// Several lines per event, easier to read during development
+    Pretty,
+    /// One json object per line, for log collectors
+    Json,
+}
@@ -0,0 +1,68 @@
+use jiff::{civil::Date, Timestamp};
+use serde::{Deserialize, Serialize};
+
+use super::config::ConfigHash;
+use super::i64_as_string;
+
+/// How periods when fetche wasn't running count towards availability
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownAs {
+    /// Left out of the availability calculation
+    #[default]
+    Exclude,
+    /// Counted as downtime
+    Down,
+    /// Counted as uptime
+    Up,
+}
+
+/// Seconds spent in each state
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub struct UptimeTotals {
+    pub up_s: i64,
+    pub down_s: i64,
+    pub unknown_s: i64,
+    pub maintenance_s: i64,
+}
+
+impl UptimeTotals {
+    /// Percentage of the counted time the config was up, None if nothing was counted
+    pub fn availability_percent(&self, unknown_as: UnknownAs) -> Option<f64> {
+        let (up, down) = match unknown_as {
+            UnknownAs::Exclude => (self.up_s, self.down_s),
+            UnknownAs::Down => (self.up_s, self.down_s + self.unknown_s),
+            UnknownAs::Up => (self.up_s + self.unknown_s, self.down_s),
+        };
+        match up + down {
+            0 => None,
+            total => Some(100.0 * up as f64 / total as f64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DailyUptime {
+    /// UTC date
+    pub date: Date,
+    #[serde(flatten)]
+    pub totals: UptimeTotals,
+    pub availability_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UptimeReport {
+    #[serde(with = "i64_as_string")]
+    pub config: ConfigHash,
+    pub since: Timestamp,
+    pub until: Timestamp,
+    pub unknown_as: UnknownAs,
+    #[serde(flatten)]
+    pub totals: UptimeTotals,
+    pub availability_percent: Option<f64>,
+    /// Availability the config should reach, in percent
+    pub sla_target: Option<f64>,
+    /// None if there is no target or no data
+    pub sla_met: Option<bool>,
+    pub days: Vec<DailyUptime>,
+}
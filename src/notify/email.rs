@@ -0,0 +1,176 @@
+use lettre::{
+    message::header::ContentType, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+use super::{render_text_template, Outcome};
+use crate::models::notification::{EmailTarget, NotificationEvent, SmtpSecurity};
+
+pub async fn send(target: &EmailTarget, event: &NotificationEvent) -> Outcome {
+    let message = match build_message(target, event) {
+        Ok(message) => message,
+        Err(e) => return Outcome::failed(e),
+    };
+
+    let builder = match target.security {
+        SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+            &target.host,
+        )),
+        SmtpSecurity::Starttls => {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&target.host)
+        }
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&target.host),
+    };
+    let mut builder = match builder {
+        Ok(builder) => builder,
+        Err(e) => return Outcome::failed(e.to_string()),
+    };
+    if let Some(port) = target.port {
+        builder = builder.port(port);
+    }
+    if let (Some(username), Some(password)) = (&target.username, &target.password) {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    match builder.build().send(message).await {
+        Ok(response) => Outcome {
+            delivered: response.is_positive(),
+            status_code: response.code().to_string().parse().ok(),
+            error: None,
+        },
+        Err(e) => Outcome::failed(e.to_string()),
+    }
+}
+
+fn build_message(target: &EmailTarget, event: &NotificationEvent) -> Result<Message, String> {
+    let from = target
+        .from
+        .parse()
+        .map_err(|e| format!("Invalid sender {}: {e}", target.from))?;
+    let mut builder = Message::builder()
+        .from(from)
+        .subject(render_text_template(&target.subject, event));
+    for to in &target.to {
+        let to = to
+            .parse()
+            .map_err(|e| format!("Invalid recipient {to}: {e}"))?;
+        builder = builder.to(to);
+    }
+    builder
+        .header(ContentType::TEXT_PLAIN)
+        .body(render_text_template(&target.body, event))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::notify::tests::event;
+
+    fn target(port: u16, security: SmtpSecurity) -> EmailTarget {
+        let mut target: EmailTarget = serde_json::from_value(serde_json::json!({
+            "host": "127.0.0.1",
+            "from": "fetche <fetche@example.com>",
+            "to": ["ops@example.com", "dev@example.com"],
+            "subject": "{{config_name}} is {{status}}",
+            "body": "Was {{previous_status}}, see record {{record}}",
+        }))
+        .unwrap();
+        target.port = Some(port);
+        target.security = security;
+        target
+    }
+
+    /// A local smtp server without STARTTLS that accepts every message,
+    /// passing on the data of every message it receives
+    async fn receiver() -> (u16, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, messages) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = stream.into_split();
+                    let mut lines = BufReader::new(read).lines();
+                    write.write_all(b"220 localhost ready\r\n").await.unwrap();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let command = line.to_uppercase();
+                        let reply: &[u8] = if command.starts_with("EHLO") {
+                            b"250 localhost\r\n"
+                        } else if command.starts_with("DATA") {
+                            write.write_all(b"354 go ahead\r\n").await.unwrap();
+                            let mut data = vec![];
+                            while let Ok(Some(line)) = lines.next_line().await {
+                                if line == "." {
+                                    break;
+                                }
+                                data.push(line);
+                            }
+                            sender.send(data.join("\n")).unwrap();
+                            b"250 queued\r\n"
+                        } else if command.starts_with("QUIT") {
+                            write.write_all(b"221 bye\r\n").await.unwrap();
+                            break;
+                        } else {
+                            b"250 ok\r\n"
+                        };
+                        write.write_all(reply).await.unwrap();
+                    }
+                });
+            }
+        });
+        (port, messages)
+    }
+
+    #[test]
+    fn messages_are_rendered_from_the_templates() {
+        let message = build_message(&target(25, SmtpSecurity::None), &event()).unwrap();
+        let formatted = String::from_utf8(message.formatted()).unwrap();
+        assert!(formatted.contains("From: fetche <fetche@example.com>\r\n"));
+        assert!(formatted.contains("To: ops@example.com, dev@example.com\r\n"));
+        assert!(formatted.contains("Subject: Products is HttpErr(503)\r\n"));
+        assert!(formatted.contains("Content-Type: text/plain"));
+        assert!(formatted.ends_with("\r\n\r\nWas HttpOk(200), see record 2"));
+    }
+
+    #[test]
+    fn invalid_addresses_are_reported() {
+        let mut invalid = target(25, SmtpSecurity::None);
+        invalid.to.push("not an address".to_owned());
+        let error = build_message(&invalid, &event()).unwrap_err();
+        assert!(
+            error.starts_with("Invalid recipient not an address"),
+            "{error}"
+        );
+        invalid.from = "nobody".to_owned();
+        let error = build_message(&invalid, &event()).unwrap_err();
+        assert!(error.starts_with("Invalid sender nobody"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn plain_smtp_delivers_the_message() {
+        let (port, mut messages) = receiver().await;
+        let outcome = send(&target(port, SmtpSecurity::None), &event()).await;
+        assert_eq!(outcome.error, None);
+        assert!(outcome.delivered);
+        assert_eq!(outcome.status_code, Some(250));
+        let message = messages.recv().await.unwrap();
+        assert!(message.contains("Subject: Products is HttpErr(503)"));
+    }
+
+    #[tokio::test]
+    async fn encrypted_modes_never_send_in_plain_text() {
+        let (port, mut messages) = receiver().await;
+        for security in [SmtpSecurity::Starttls, SmtpSecurity::Tls] {
+            let outcome = send(&target(port, security), &event()).await;
+            assert!(!outcome.delivered, "{security:?}");
+            assert!(outcome.error.is_some(), "{security:?}");
+        }
+        assert!(messages.try_recv().is_err());
+    }
+}
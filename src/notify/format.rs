@@ -0,0 +1,317 @@
+use serde_json::json;
+
+use crate::html::escape;
+use crate::models::notification::{NotificationEvent, Trigger, WebhookFormat};
+
+/// Discord embed colors
+const COLOR_RED: u32 = 0xd9_3f_3f;
+const COLOR_GREEN: u32 = 0x3f_b9_50;
+const COLOR_BLUE: u32 = 0x3f_7f_d9;
+
+/// A rendered webhook request body
+pub struct Payload {
+    pub content_type: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+}
+
+pub fn render(format: WebhookFormat, event: &NotificationEvent) -> Payload {
+    match format {
+        WebhookFormat::Json => Payload {
+            content_type: "application/json",
+            headers: vec![],
+            body: serde_json::to_string(event).unwrap(),
+        },
+        WebhookFormat::Slack => json_payload(slack(event)),
+        WebhookFormat::Discord => json_payload(discord(event)),
+        WebhookFormat::Matrix => json_payload(matrix(event)),
+        WebhookFormat::Ntfy => ntfy(event),
+    }
+}
+
+fn json_payload(body: serde_json::Value) -> Payload {
+    Payload {
+        content_type: "application/json",
+        headers: vec![],
+        body: body.to_string(),
+    }
+}
+
+fn config_name(event: &NotificationEvent) -> &str {
+    event.config_name.as_deref().unwrap_or(&event.source_url)
+}
+
+/// One line summary, such as "api: status changed"
+fn title(event: &NotificationEvent) -> String {
+    let name = config_name(event);
+    let alert = event.alert.as_deref().unwrap_or_default();
+    match event.trigger {
+        Trigger::StatusChange => format!("{name}: status changed"),
+        Trigger::BodyChange => format!("{name}: response changed"),
+        Trigger::AlertFiring => format!("{name}: alert {alert} firing"),
+        Trigger::AlertResolved => format!("{name}: alert {alert} resolved"),
+        Trigger::FlappingStarted => format!("{name}: flapping"),
+        Trigger::FlappingStopped => format!("{name}: stable again"),
+    }
+}
+
+fn status_line(event: &NotificationEvent) -> String {
+    if event.previous_status == event.status {
+        event.status.to_string()
+    } else {
+        format!("{} -> {}", event.previous_status, event.status)
+    }
+}
+
+fn latency_line(event: &NotificationEvent) -> Option<String> {
+    event.latency_ms.map(|ms| format!("{ms} ms"))
+}
+
+/// Whether the event is good news, bad news or neither
+fn is_recovery(event: &NotificationEvent) -> Option<bool> {
+    match event.trigger {
+        Trigger::AlertResolved | Trigger::FlappingStopped => Some(true),
+        Trigger::AlertFiring | Trigger::FlappingStarted => Some(false),
+        Trigger::StatusChange => Some(!event.status.is_failure()),
+        Trigger::BodyChange => None,
+    }
+}
+
+/// Keeps a diff from closing the code block it is shown in
+fn fenced(diff: &str) -> String {
+    format!("```\n{}\n```", diff.replace("```", "`\u{200b}``"))
+}
+
+/// Slack treats these as control characters, everything else is shown as is
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn slack(event: &NotificationEvent) -> serde_json::Value {
+    let mut lines = vec![
+        format!("*{}*", slack_escape(&title(event))),
+        format!("<{}>", slack_escape(&event.source_url)),
+        format!("Status: {}", slack_escape(&status_line(event))),
+    ];
+    if let Some(latency) = latency_line(event) {
+        lines.push(format!("Latency: {latency}"));
+    }
+    if let Some(message) = &event.message {
+        lines.push(slack_escape(message));
+    }
+    if let Some(diff) = event.diff.as_deref().filter(|diff| !diff.is_empty()) {
+        lines.push(fenced(&slack_escape(diff)));
+    }
+    json!({ "text": lines.join("\n") })
+}
+
+fn discord(event: &NotificationEvent) -> serde_json::Value {
+    let mut description = vec![];
+    if let Some(message) = &event.message {
+        description.push(message.clone());
+    }
+    if let Some(diff) = event.diff.as_deref().filter(|diff| !diff.is_empty()) {
+        description.push(fenced(diff));
+    }
+    let mut fields = vec![json!({ "name": "Status", "value": status_line(event), "inline": true })];
+    if let Some(latency) = latency_line(event) {
+        fields.push(json!({ "name": "Latency", "value": latency, "inline": true }));
+    }
+    let color = match is_recovery(event) {
+        Some(true) => COLOR_GREEN,
+        Some(false) => COLOR_RED,
+        None => COLOR_BLUE,
+    };
+    json!({
+        "content": title(event),
+        "embeds": [{
+            "title": config_name(event),
+            "url": event.source_url,
+            "description": description.join("\n"),
+            "color": color,
+            "fields": fields,
+            "timestamp": event.fetched_at.to_string(),
+        }],
+    })
+}
+
+fn matrix(event: &NotificationEvent) -> serde_json::Value {
+    let title = title(event);
+    let status = status_line(event);
+    let mut body = vec![
+        title.clone(),
+        event.source_url.clone(),
+        format!("Status: {status}"),
+    ];
+    let mut html = vec![
+        format!("<strong>{}</strong>", escape(&title)),
+        format!("<a href=\"{0}\">{0}</a>", escape(&event.source_url)),
+        format!("Status: {}", escape(&status)),
+    ];
+    if let Some(latency) = latency_line(event) {
+        body.push(format!("Latency: {latency}"));
+        html.push(format!("Latency: {latency}"));
+    }
+    if let Some(message) = &event.message {
+        body.push(message.clone());
+        html.push(escape(message));
+    }
+    let mut formatted_body = html.join("<br>");
+    if let Some(diff) = event.diff.as_deref().filter(|diff| !diff.is_empty()) {
+        body.push(diff.to_owned());
+        formatted_body.push_str(&format!("<pre><code>{}</code></pre>", escape(diff)));
+    }
+    json!({
+        "msgtype": "m.text",
+        "body": body.join("\n"),
+        "format": "org.matrix.custom.html",
+        "formatted_body": formatted_body,
+    })
+}
+
+fn ntfy(event: &NotificationEvent) -> Payload {
+    let mut lines = vec![
+        event.source_url.clone(),
+        format!("Status: {}", status_line(event)),
+    ];
+    if let Some(latency) = latency_line(event) {
+        lines.push(format!("Latency: {latency}"));
+    }
+    if let Some(message) = &event.message {
+        lines.push(message.clone());
+    }
+    if let Some(diff) = event.diff.as_deref().filter(|diff| !diff.is_empty()) {
+        lines.push(diff.to_owned());
+    }
+    let tags = match is_recovery(event) {
+        Some(true) => "white_check_mark",
+        Some(false) => "rotating_light",
+        None => "pencil",
+    };
+    Payload {
+        content_type: "text/plain; charset=utf-8",
+        headers: vec![
+            ("Title", header_value(&title(event))),
+            ("Tags", tags.to_owned()),
+            ("Click", event.source_url.clone()),
+        ],
+        body: lines.join("\n"),
+    }
+}
+
+/// Header values have to be a single line of ascii, anything else is sent
+/// as an RFC 2047 encoded word, which ntfy decodes
+fn header_value(text: &str) -> String {
+    let line: String = text
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    if line.is_ascii() {
+        return line;
+    }
+    let mut encoded = "=?UTF-8?Q?".to_owned();
+    for byte in line.bytes() {
+        match byte {
+            b' ' => encoded.push('_'),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'!' | b'*' | b'+' | b'-' | b'/' => {
+                encoded.push(char::from(byte))
+            }
+            _ => encoded.push_str(&format!("={byte:02X}")),
+        }
+    }
+    encoded.push_str("?=");
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::tests::event;
+
+    fn with_diff(diff: &str) -> NotificationEvent {
+        let mut event = event();
+        event.diff = Some(diff.to_owned());
+        event
+    }
+
+    fn json_body(payload: &Payload) -> serde_json::Value {
+        assert_eq!(payload.content_type, "application/json");
+        serde_json::from_str(&payload.body).unwrap()
+    }
+
+    #[test]
+    fn json_sends_the_event_itself() {
+        let body = json_body(&render(WebhookFormat::Json, &event()));
+        assert_eq!(body["trigger"], "status_change");
+        assert_eq!(body["config_name"], "Products");
+        assert_eq!(body["record"], 2);
+    }
+
+    #[test]
+    fn slack_escapes_control_characters_and_fences() {
+        let mut event = with_diff("- <b>a</b>\n+ ```b```");
+        event.config_name = Some("A & B".to_owned());
+        let body = json_body(&render(WebhookFormat::Slack, &event));
+        assert_eq!(
+            body["text"],
+            "*A &amp; B: status changed*\n<https://example.com/products>\n\
+             Status: HttpOk(200) -&gt; HttpErr(503)\nLatency: 120 ms\n\
+             ```\n- &lt;b&gt;a&lt;/b&gt;\n+ `\u{200b}``b`\u{200b}``\n```"
+        );
+    }
+
+    #[test]
+    fn discord_keeps_diffs_inside_their_code_block() {
+        let body = json_body(&render(WebhookFormat::Discord, &with_diff("```\nx")));
+        assert_eq!(body["content"], "Products: status changed");
+        let embed = &body["embeds"][0];
+        assert_eq!(embed["title"], "Products");
+        assert_eq!(embed["color"], COLOR_RED);
+        assert_eq!(embed["description"], "```\n`\u{200b}``\nx\n```");
+        assert_eq!(embed["fields"][0]["value"], "HttpOk(200) -> HttpErr(503)");
+        assert_eq!(embed["timestamp"], "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn matrix_escapes_the_html_body() {
+        let body = json_body(&render(WebhookFormat::Matrix, &with_diff("<a>")));
+        assert_eq!(
+            body["body"],
+            "Products: status changed\nhttps://example.com/products\n\
+             Status: HttpOk(200) -> HttpErr(503)\nLatency: 120 ms\n<a>"
+        );
+        assert_eq!(
+            body["formatted_body"],
+            "<strong>Products: status changed</strong><br>\
+             <a href=\"https://example.com/products\">https://example.com/products</a><br>\
+             Status: HttpOk(200) -&gt; HttpErr(503)<br>Latency: 120 ms\
+             <pre><code>&lt;a&gt;</code></pre>"
+        );
+    }
+
+    #[test]
+    fn ntfy_titles_are_valid_header_values() {
+        let payload = render(WebhookFormat::Ntfy, &event());
+        assert_eq!(payload.content_type, "text/plain; charset=utf-8");
+        assert_eq!(
+            payload.headers[0],
+            ("Title", "Products: status changed".to_owned())
+        );
+        assert_eq!(payload.headers[1], ("Tags", "rotating_light".to_owned()));
+        assert_eq!(
+            payload.body,
+            "https://example.com/products\nStatus: HttpOk(200) -> HttpErr(503)\nLatency: 120 ms"
+        );
+
+        let mut event = event();
+        event.config_name = Some("Café\nmenu".to_owned());
+        let payload = render(WebhookFormat::Ntfy, &event);
+        assert_eq!(
+            payload.headers[0].1,
+            "=?UTF-8?Q?Caf=C3=A9_menu=3A_status_changed?="
+        );
+        assert!(reqwest::header::HeaderValue::from_str(&payload.headers[0].1).is_ok());
+    }
+}
@@ -0,0 +1,376 @@
+use std::mem::discriminant;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use jiff::Timestamp;
+use tracing::Instrument;
+
+use crate::db::{record_delivery, DBConn};
+use crate::diff::{diff_records, render_diff};
+use crate::models::{
+    config::Config,
+    fetch_result::{FetchRecord, Status},
+    notification::{Delivery, NotificationEvent, NotificationKind, NotificationTarget, Trigger},
+};
+
+mod email;
+mod format;
+mod webhook;
+
+/// Delay before the first retry, doubled for every retry after that
+const RETRY_BASE_DELAY_S: u64 = 2;
+/// Retries are never delayed longer than this
+const MAX_RETRY_DELAY_S: u64 = 60 * 60;
+/// A webhook that doesn't answer within this time counts as a failed attempt
+const REQUEST_TIMEOUT_S: u64 = 10;
+/// Diffs included in notifications are cut off after this many characters
+const DIFF_EXCERPT_CHARS: usize = 1500;
+
+static NOTIFIER: OnceLock<Notifier> = OnceLock::new();
+
+pub struct Notifier {
+    targets: Vec<NotificationTarget>,
+    client: reqwest::Client,
+}
+
+/// Sets up the notification targets defined in fetche.toml
+/// - without calling this, events are detected but not sent anywhere
+pub fn init(targets: Vec<NotificationTarget>) {
+    let notifier = Notifier {
+        targets,
+        client: reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_S))
+            .build()
+            .expect("Failed to create the notification http client"),
+    };
+    if NOTIFIER.set(notifier).is_err() {
+        tracing::warn!("Notifier was already initialized, ignoring");
+    }
+}
+
+/// The result of a single delivery attempt
+pub struct Outcome {
+    pub delivered: bool,
+    /// Http status or smtp reply code returned by the target
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl Outcome {
+    fn failed(error: String) -> Self {
+        Self {
+            delivered: false,
+            status_code: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Works out which triggers fire when `current` is recorded after `previous`
+/// - records with an Unknown status never trigger anything, fetche wasn't running then
+/// - a body change is only reported if the status stayed the same
+pub fn detect(previous: &FetchRecord, current: &FetchRecord, config: &Config) -> Vec<Trigger> {
+    let mut triggers = vec![];
+    if previous.status == Status::Unknown || current.status == Status::Unknown {
+        return triggers;
+    }
+    if discriminant(&previous.status) != discriminant(&current.status) {
+        // A different body is expected when the status changes, no need to mention it twice
+        triggers.push(Trigger::StatusChange);
+    } else if previous.body_text.is_some()
+        && current.body_text.is_some()
+        && !previous.body_matches(current, config.compare, config.canonicalize.as_ref())
+    {
+        triggers.push(Trigger::BodyChange);
+    }
+    triggers
+}
+
+/// The diff between two records as text, short enough for a chat message
+pub fn diff_excerpt(previous: &FetchRecord, current: &FetchRecord) -> String {
+    let diff = render_diff(&diff_records(previous, current).diff);
+    match diff.char_indices().nth(DIFF_EXCERPT_CHARS) {
+        Some((cut, _)) => format!("{}\n…", &diff[..cut]),
+        None => diff,
+    }
+}
+
+/// Sends the events to every target subscribed to them, in the background
+pub fn dispatch(db: &DBConn, events: Vec<NotificationEvent>) {
+    let Some(notifier) = NOTIFIER.get() else {
+        return;
+    };
+    for event in events {
+        for target in notifier
+            .targets
+            .iter()
+            .filter(|target| target.on.contains(&event.trigger))
+        {
+            let db = db.clone();
+            let client = notifier.client.clone();
+            let target = target.clone();
+            let event = event.clone();
+            let span = tracing::info_span!(
+                "notify",
+                target = %target.name,
+                trigger = ?event.trigger
+            );
+            tokio::spawn(
+                async move {
+                    deliver(&db, &client, &target, &event).await;
+                }
+                .instrument(span),
+            );
+        }
+    }
+}
+
+async fn deliver(
+    db: &DBConn,
+    client: &reqwest::Client,
+    target: &NotificationTarget,
+    event: &NotificationEvent,
+) {
+    for attempt in 1..=target.retries.saturating_add(1) {
+        if attempt > 1 {
+            tokio::time::sleep(retry_delay(attempt)).await;
+        }
+
+        let outcome = match &target.kind {
+            NotificationKind::Webhook(webhook) => webhook::send(client, webhook, event).await,
+            NotificationKind::Email(email) => email::send(email, event).await,
+        };
+        let delivered = outcome.delivered;
+        let logged = record_delivery(
+            db,
+            Delivery {
+                id: None,
+                target: target.name.clone(),
+                config: event.config,
+                record: event.record,
+                trigger: event.trigger,
+                attempt: attempt as i64,
+                status_code: outcome.status_code,
+                error: outcome.error,
+                delivered,
+                created_at: Timestamp::now(),
+            },
+        )
+        .await;
+        if let Err(e) = logged {
+            tracing::error!(target = %target.name, error = %e, "Failed to log notification delivery");
+        }
+
+        if delivered {
+            return;
+        }
+    }
+    tracing::warn!(
+        target = %target.name,
+        config = %event.config,
+        "Giving up on notification"
+    );
+}
+
+/// How long to wait before the given attempt, which has to be a retry
+fn retry_delay(attempt: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY_S.saturating_mul(2u64.saturating_pow(attempt - 2));
+    Duration::from_secs(delay.min(MAX_RETRY_DELAY_S))
+}
+
+/// Replaces `{{name}}` placeholders with the event's values
+/// - values are escaped for use inside json strings
+pub fn render_json_template(template: &str, event: &NotificationEvent) -> String {
+    render_template(template, event, |value| {
+        let escaped = serde_json::to_string(value).unwrap();
+        escaped[1..escaped.len() - 1].to_owned()
+    })
+}
+
+/// Replaces `{{name}}` placeholders with the event's values as they are
+pub fn render_text_template(template: &str, event: &NotificationEvent) -> String {
+    render_template(template, event, str::to_owned)
+}
+
+fn render_template(
+    template: &str,
+    event: &NotificationEvent,
+    escape: impl Fn(&str) -> String,
+) -> String {
+    // A single pass, so that placeholders inside the values are left alone
+    let vars = event.template_vars();
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find("{{") {
+        rendered.push_str(&rest[..open]);
+        let after = &rest[open + 2..];
+        let var = after.find("}}").and_then(|close| {
+            vars.iter()
+                .find(|(name, _)| *name == &after[..close])
+                .map(|(_, value)| (close, value))
+        });
+        match var {
+            Some((close, value)) => {
+                rendered.push_str(&escape(value));
+                rest = &after[close + 2..];
+            }
+            None => {
+                rendered.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::db::{create_or_activate_config, get_deliveries};
+    use crate::models::config::ConfigInput;
+
+    pub(super) fn event() -> NotificationEvent {
+        NotificationEvent {
+            trigger: Trigger::StatusChange,
+            config: 42,
+            config_name: Some("Products".to_owned()),
+            source_url: "https://example.com/products".to_owned(),
+            previous_status: Status::HttpOk(200),
+            status: Status::HttpErr(503),
+            fetched_at: Timestamp::new(1_700_000_000, 0).unwrap(),
+            previous_record: Some(1),
+            record: Some(2),
+            alert: None,
+            message: None,
+            latency_ms: Some(120),
+            diff: None,
+        }
+    }
+
+    #[test]
+    fn templates_are_rendered_in_a_single_pass() {
+        let mut event = event();
+        event.message = Some(r#"said "{{status}}""#.to_owned());
+        assert_eq!(
+            render_text_template("{{config_name}}: {{message}} {{unknown}} {{", &event),
+            r#"Products: said "{{status}}" {{unknown}} {{"#
+        );
+        assert_eq!(
+            render_json_template(r#"{"text":"{{message}}","record":{{record}}}"#, &event),
+            r#"{"text":"said \"{{status}}\"","record":2}"#
+        );
+    }
+
+    #[test]
+    fn retry_delays_double_up_to_a_limit() {
+        assert_eq!(retry_delay(2), Duration::from_secs(2));
+        assert_eq!(retry_delay(3), Duration::from_secs(4));
+        assert_eq!(retry_delay(5), Duration::from_secs(16));
+        assert_eq!(retry_delay(100), Duration::from_secs(MAX_RETRY_DELAY_S));
+        assert_eq!(
+            retry_delay(u32::MAX),
+            Duration::from_secs(MAX_RETRY_DELAY_S)
+        );
+    }
+
+    /// An in-memory database with a config to attach deliveries to
+    async fn test_db() -> (DBConn, Config) {
+        let db = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&db).await.unwrap();
+        let input: ConfigInput = serde_json::from_value(serde_json::json!({
+            "source_url": "https://example.com/products",
+            "fetch_interval_s": 60,
+            "try_parse_json": true,
+        }))
+        .unwrap();
+        let config = Config::from(input);
+        create_or_activate_config(&db, config.clone())
+            .await
+            .unwrap();
+        (db, config)
+    }
+
+    /// A local http server answering requests with the given statuses in turn,
+    /// passing on the body of every request it receives
+    async fn receiver(statuses: Vec<u16>) -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (sender, bodies) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![];
+                let mut buf = [0; 4096];
+                let body = loop {
+                    let read = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length || read == 0 {
+                        break body.to_owned();
+                    }
+                };
+                sender.send(body).unwrap();
+                let response = format!(
+                    "HTTP/1.1 {status} Whatever\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, bodies)
+    }
+
+    #[tokio::test]
+    async fn failed_deliveries_are_retried_and_logged() {
+        let (db, config) = test_db().await;
+        let (url, mut bodies) = receiver(vec![500, 204]).await;
+        let target: NotificationTarget = serde_json::from_value(serde_json::json!({
+            "name": "local",
+            "type": "webhook",
+            "url": url,
+            "retries": 1,
+            "template": r#"{"status":"{{status}}","config":"{{config}}"}"#,
+        }))
+        .unwrap();
+        let mut event = event();
+        event.config = config.hash;
+        event.record = None;
+        event.previous_record = None;
+
+        deliver(&db, &reqwest::Client::new(), &target, &event).await;
+
+        let expected = format!(r#"{{"status":"HttpErr(503)","config":"{}"}}"#, config.hash);
+        for _ in 0..2 {
+            assert_eq!(bodies.recv().await.unwrap(), expected);
+        }
+        let deliveries = get_deliveries(&db, 10).await.unwrap();
+        let attempts: Vec<_> = deliveries
+            .iter()
+            .map(|delivery| (delivery.attempt, delivery.status_code, delivery.delivered))
+            .collect();
+        assert_eq!(attempts, vec![(2, Some(204), true), (1, Some(500), false)]);
+        assert!(deliveries
+            .iter()
+            .all(|delivery| delivery.target == "local" && delivery.config == config.hash));
+    }
+}
@@ -0,0 +1,44 @@
+use reqwest::{header::CONTENT_TYPE, Method};
+
+use super::format::{self, Payload};
+use super::{render_json_template, render_text_template, Outcome};
+use crate::models::notification::{NotificationEvent, WebhookTarget};
+
+pub async fn send(
+    client: &reqwest::Client,
+    target: &WebhookTarget,
+    event: &NotificationEvent,
+) -> Outcome {
+    let method = match Method::from_bytes(target.method.to_uppercase().as_bytes()) {
+        Ok(method) => method,
+        Err(e) => return Outcome::failed(format!("Invalid method {}: {e}", target.method)),
+    };
+    let payload = match &target.template {
+        Some(template) => Payload {
+            content_type: "application/json",
+            headers: vec![],
+            body: render_json_template(template, event),
+        },
+        None => format::render(target.format, event),
+    };
+
+    let mut request = client
+        .request(method, render_text_template(&target.url, event))
+        .header(CONTENT_TYPE, payload.content_type)
+        .body(payload.body);
+    for (name, value) in payload.headers {
+        request = request.header(name, value);
+    }
+    for (name, value) in &target.headers {
+        request = request.header(name, value);
+    }
+
+    match request.send().await {
+        Ok(resp) => Outcome {
+            delivered: resp.status().is_success(),
+            status_code: Some(resp.status().as_u16()),
+            error: None,
+        },
+        Err(e) => Outcome::failed(e.to_string()),
+    }
+}
@@ -0,0 +1,45 @@
+use jiff::Timestamp;
+
+use crate::models::{
+    compare::{canonicalize_value, CompareMode},
+    config::Config,
+    fetch_result::PublicFetchRecord,
+    probe::{ExtractedValue, Probe},
+};
+use crate::request;
+
+/// Fetches a config with all of its settings and reports what would be recorded
+/// - nothing is stored and no notifications are sent
+pub async fn probe(config: &Config) -> Probe {
+    let (mut record, error) = request(config, Timestamp::now()).await;
+    record.from_db = false;
+    let body = record.body_text.as_deref();
+    let extracted = config
+        .extract
+        .iter()
+        .map(|extract| ExtractedValue {
+            name: extract.name.clone(),
+            pointer: extract.pointer.clone(),
+            value: body.and_then(|body| extract.read(body)),
+        })
+        .collect();
+    let compared_body = match (config.compare, record.valid_json) {
+        (CompareMode::SemanticJson, Some(true)) => body
+            .and_then(|body| serde_json::from_str(body).ok())
+            .map(|mut value| {
+                if let Some(canonicalize) = &config.canonicalize {
+                    canonicalize_value(&mut value, canonicalize);
+                }
+                value
+            }),
+        _ => None,
+    };
+    Probe {
+        config: config.hash,
+        valid_json: record.valid_json,
+        record: PublicFetchRecord::from(record),
+        error,
+        extracted,
+        compared_body,
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use jiff::{tz::TimeZone, Timestamp, ToSpan};
+
+use crate::db::{get_all_configs, get_incidents, get_latest_states, DBConn};
+use crate::maintenance;
+use crate::models::{
+    config::Config,
+    fetch_result::Status,
+    status::LatestState,
+    status_page::{
+        PublicComponent, PublicDay, PublicGroup, PublicIncident, PublicState, StatusPage,
+        StatusPageComponent, StatusPageConfig,
+    },
+    uptime::UnknownAs,
+};
+use crate::uptime::report;
+
+/// A rendered page is reused for this long, the page is public and can get a lot of traffic
+const CACHE_S: i64 = 60;
+/// A config that wasn't fetched for this many intervals is shown as unknown
+const STALE_INTERVALS: i64 = 3;
+
+static STATUS_PAGE: OnceLock<StatusPageConfig> = OnceLock::new();
+static CACHE: Mutex<Option<StatusPage>> = Mutex::new(None);
+
+/// Sets up the status page defined in fetche.toml
+pub fn init(config: StatusPageConfig) {
+    if let Err(e) = config.validate() {
+        panic!("Invalid status page settings: {e}");
+    }
+    if STATUS_PAGE.set(config).is_err() {
+        tracing::warn!("Status page was already initialized, ignoring");
+    }
+}
+
+pub fn is_enabled() -> bool {
+    STATUS_PAGE.get().is_some_and(|config| config.enabled)
+}
+
+/// The current status page, None unless it is enabled
+/// - components whose config doesn't exist or isn't active are left out
+pub async fn render(db: &DBConn) -> Result<Option<StatusPage>, sqlx::Error> {
+    let Some(page_config) = STATUS_PAGE.get().filter(|config| config.enabled) else {
+        return Ok(None);
+    };
+    let now = Timestamp::now();
+    if let Some(page) = CACHE.lock().unwrap().as_ref() {
+        if now.as_second() - page.generated_at.as_second() < CACHE_S {
+            return Ok(Some(page.clone()));
+        }
+    }
+
+    let configs: Vec<_> = get_all_configs(db)
+        .await?
+        .into_iter()
+        .filter(|config| config.active)
+        .collect();
+    let mut states: HashMap<_, _> = get_latest_states(db)
+        .await?
+        .into_iter()
+        .map(|state| (state.config, state))
+        .collect();
+    let first_day = now
+        .to_zoned(TimeZone::UTC)
+        .date()
+        .checked_sub((page_config.history_days - 1).days())
+        .unwrap();
+    let since = first_day.to_zoned(TimeZone::UTC).unwrap().timestamp();
+
+    let mut groups = vec![];
+    for group in &page_config.groups {
+        let mut components = vec![];
+        for component in &group.components {
+            let Some(config) = configs
+                .iter()
+                .find(|config| config.is_selected_by(&component.config))
+            else {
+                continue;
+            };
+            let latest = states.remove(&config.hash);
+            components.push(
+                render_component(db, page_config, component, config, latest, since, now).await?,
+            );
+        }
+        groups.push(PublicGroup {
+            name: group.name.clone(),
+            state: worst(components.iter().map(|component| component.state)),
+            components,
+        });
+    }
+    let page = StatusPage {
+        title: page_config.title.clone(),
+        generated_at: now,
+        state: worst(groups.iter().map(|group| group.state)),
+        groups,
+    };
+    *CACHE.lock().unwrap() = Some(page.clone());
+    Ok(Some(page))
+}
+
+async fn render_component(
+    db: &DBConn,
+    page_config: &StatusPageConfig,
+    component: &StatusPageComponent,
+    config: &Config,
+    latest: Option<LatestState>,
+    since: Timestamp,
+    now: Timestamp,
+) -> Result<PublicComponent, sqlx::Error> {
+    let state = if maintenance::is_active(db, config, now).await? {
+        PublicState::Maintenance
+    } else {
+        match latest {
+            Some(latest) if !config.paused && !is_stale(config, &latest, now) => {
+                match latest.status {
+                    Status::HttpOk(_) => PublicState::Operational,
+                    Status::AssertionFailed(_) => PublicState::Degraded,
+                    Status::HttpErr(_) | Status::Error => PublicState::Down,
+                    Status::Unknown => PublicState::Unknown,
+                }
+            }
+            _ => PublicState::Unknown,
+        }
+    };
+
+    let uptime = report(db, config, since, now, UnknownAs::Exclude).await?;
+    let first_day = since.to_zoned(TimeZone::UTC).date();
+    let days = (0..page_config.history_days)
+        .map(|offset| {
+            let date = first_day.checked_add(offset.days()).unwrap();
+            PublicDay {
+                date,
+                availability_percent: uptime
+                    .days
+                    .iter()
+                    .find(|day| day.date == date)
+                    .and_then(|day| day.availability_percent),
+            }
+        })
+        .collect();
+
+    let mut incidents: Vec<_> = get_incidents(db, Some(config.hash), since)
+        .await?
+        .into_iter()
+        .map(|incident| PublicIncident {
+            started_at: incident.started_at,
+            ended_at: incident.ended_at,
+            duration_s: incident.duration_s,
+        })
+        .collect();
+    incidents.sort_by_key(|incident| std::cmp::Reverse(incident.started_at));
+
+    Ok(PublicComponent {
+        name: component.display_name.clone(),
+        state,
+        availability_percent: uptime.availability_percent,
+        days,
+        incidents,
+    })
+}
+
+fn is_stale(config: &Config, latest: &LatestState, now: Timestamp) -> bool {
+    let allowed_s = STALE_INTERVALS * config.fetch_interval_s;
+    now.as_second() - latest.fetched_at.as_second() > allowed_s
+}
+
+/// Unknown if there is nothing to judge by
+fn worst(states: impl Iterator<Item = PublicState>) -> PublicState {
+    states.max().unwrap_or(PublicState::Unknown)
+}
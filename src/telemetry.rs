@@ -0,0 +1,65 @@
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator, runtime, trace::TracerProvider, Resource,
+};
+use std::fs::OpenOptions;
+use std::sync::Mutex;
+
+use tracing_subscriber::{
+    fmt::writer::BoxMakeWriter, layer::SubscriberExt, EnvFilter, Layer, Registry,
+};
+
+use crate::models::telemetry::{LogFormat, LoggingConfig, TelemetryConfig};
+
+/// Installs the global tracing subscriber, which logs events and exports spans over OTLP
+/// if an endpoint is set
+/// - the returned provider has to be shut down before exiting, to flush pending spans
+pub fn init(config: &TelemetryConfig, logging: &LoggingConfig) -> Option<TracerProvider> {
+    let provider = config.otlp_endpoint.as_ref().map(|endpoint| {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()
+            .expect("Failed to create the OTLP exporter");
+        TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                config.service_name.clone(),
+            )]))
+            .build()
+    });
+    let otel = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("fetche")));
+    // Lets api requests continue traces started by their callers
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&logging.filter))
+        .expect("Invalid log filter");
+    let writer = match &logging.file {
+        Some(path) => BoxMakeWriter::new(Mutex::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .expect("Failed to open the log file"),
+        )),
+        None => BoxMakeWriter::new(std::io::stdout),
+    };
+    let fmt = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(logging.file.is_none());
+    let fmt = match logging.format {
+        LogFormat::Text => fmt.boxed(),
+        LogFormat::Pretty => fmt.pretty().boxed(),
+        LogFormat::Json => fmt.json().boxed(),
+    };
+
+    let subscriber = Registry::default().with(filter).with(otel).with(fmt);
+    tracing::subscriber::set_global_default(subscriber)
+        .expect("Failed to install the tracing subscriber");
+    provider
+}
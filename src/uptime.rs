@@ -0,0 +1,319 @@
+use jiff::{tz::TimeZone, Timestamp};
+
+use crate::db::{get_maintenance_windows, get_record_before, get_records_between, DBConn};
+use crate::models::{
+    config::Config,
+    fetch_result::{FetchRecord, Status},
+    uptime::{DailyUptime, UnknownAs, UptimeReport, UptimeTotals},
+};
+
+const DAY_S: i64 = 24 * 60 * 60;
+
+/// Computes the availability of a config over [since, until) from its status history
+/// - every record's status lasts until the next record
+/// - after the latest fetch (plus one interval) the status is unknown
+/// - time before the first record isn't counted at all
+/// - time spent in the config's maintenance windows is left out of the availability,
+///   whether or not a record was stored during the window
+pub async fn report(
+    db: &DBConn,
+    config: &Config,
+    since: Timestamp,
+    until: Timestamp,
+    unknown_as: UnknownAs,
+) -> Result<UptimeReport, sqlx::Error> {
+    let until = until.min(Timestamp::now());
+    let mut records = get_records_between(db, Some(config.hash), since, until).await?;
+    if let Some(previous) = get_record_before(db, config.hash, since).await? {
+        records.insert(0, previous);
+    }
+
+    let maintenance: Vec<_> = get_maintenance_windows(db)
+        .await?
+        .iter()
+        .filter(|window| window.applies_to(config))
+        .flat_map(|window| window.schedule.active_ranges(since, until))
+        .map(|(start, end)| (start.as_second(), end.as_second()))
+        .collect();
+
+    let segments = overlay_maintenance(&segments(&records, config, since, until), &maintenance);
+    let (totals, days) = split_days(&segments, unknown_as);
+
+    let availability_percent = totals.availability_percent(unknown_as);
+    Ok(UptimeReport {
+        config: config.hash,
+        since,
+        until,
+        unknown_as,
+        totals,
+        availability_percent,
+        sla_target: config.sla_target,
+        sla_met: config
+            .sla_target
+            .zip(availability_percent)
+            .map(|(target, availability)| availability >= target),
+        days,
+    })
+}
+
+/// (start, end, status, maintenance) in seconds, clipped to the window
+type Segment = (i64, i64, Status, bool);
+
+/// Turns records, oldest first, into the periods each status lasted within [since, until)
+fn segments(
+    records: &[FetchRecord],
+    config: &Config,
+    since: Timestamp,
+    until: Timestamp,
+) -> Vec<Segment> {
+    let (since, until) = (since.as_second(), until.as_second());
+    let mut segments = vec![];
+    for (i, record) in records.iter().enumerate() {
+        let start = record.fetched_at.as_second().max(since);
+        let end = match records.get(i + 1) {
+            Some(next) => next.fetched_at.as_second(),
+            None => {
+                let last_known = config
+                    .last_fetched
+                    .map(|last| last.as_second() + config.fetch_interval_s)
+                    .unwrap_or(start)
+                    .max(start)
+                    .min(until);
+                segments.push((last_known, until, Status::Unknown, false));
+                last_known
+            }
+        };
+        segments.push((start, end.min(until), record.status, false));
+    }
+    segments
+}
+
+/// Splits the segments where maintenance starts or ends and marks the parts within maintenance
+fn overlay_maintenance(segments: &[Segment], maintenance: &[(i64, i64)]) -> Vec<Segment> {
+    let mut overlaid = vec![];
+    for &(start, end, status, _) in segments {
+        let mut cuts: Vec<_> = maintenance
+            .iter()
+            .flat_map(|&(from, to)| [from, to])
+            .filter(|cut| start < *cut && *cut < end)
+            .collect();
+        cuts.sort_unstable();
+        cuts.dedup();
+        let mut piece_start = start;
+        for piece_end in cuts.into_iter().chain([end]) {
+            let in_maintenance = maintenance
+                .iter()
+                .any(|&(from, to)| from <= piece_start && piece_start < to);
+            overlaid.push((piece_start, piece_end, status, in_maintenance));
+            piece_start = piece_end;
+        }
+    }
+    overlaid
+}
+
+/// Adds up the segments, in total and per UTC day
+fn split_days(segments: &[Segment], unknown_as: UnknownAs) -> (UptimeTotals, Vec<DailyUptime>) {
+    let mut totals = UptimeTotals::default();
+    let mut days: Vec<DailyUptime> = vec![];
+    for &(start, end, status, maintenance) in segments {
+        let mut start = start;
+        while start < end {
+            let day_start = start.div_euclid(DAY_S) * DAY_S;
+            let chunk_end = end.min(day_start + DAY_S);
+            let seconds = chunk_end - start;
+            let date = Timestamp::new(day_start, 0)
+                .unwrap()
+                .to_zoned(TimeZone::UTC)
+                .date();
+            let day = match days.iter_mut().find(|day| day.date == date) {
+                Some(day) => day,
+                None => {
+                    days.push(DailyUptime {
+                        date,
+                        totals: UptimeTotals::default(),
+                        availability_percent: None,
+                    });
+                    days.last_mut().unwrap()
+                }
+            };
+            add_seconds(&mut totals, status, maintenance, seconds);
+            add_seconds(&mut day.totals, status, maintenance, seconds);
+            start = chunk_end;
+        }
+    }
+    days.sort_by_key(|day| day.date);
+    for day in &mut days {
+        day.availability_percent = day.totals.availability_percent(unknown_as);
+    }
+    (totals, days)
+}
+
+fn add_seconds(totals: &mut UptimeTotals, status: Status, maintenance: bool, seconds: i64) {
+    if maintenance {
+        totals.maintenance_s += seconds;
+        return;
+    }
+    match status {
+        Status::HttpOk(_) => totals.up_s += seconds,
+        Status::HttpErr(_) | Status::AssertionFailed(_) | Status::Error => totals.down_s += seconds,
+        Status::Unknown => totals.unknown_s += seconds,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::ConfigInput;
+
+    fn config(last_fetched: Option<i64>) -> Config {
+        let input: ConfigInput = serde_json::from_value(serde_json::json!({
+            "source_url": "https://example.com",
+            "fetch_interval_s": 60,
+            "try_parse_json": false,
+        }))
+        .unwrap();
+        let mut config = Config::from(input);
+        config.last_fetched = last_fetched.map(|at| Timestamp::new(at, 0).unwrap());
+        config
+    }
+
+    fn record(fetched_at: i64, status: Status) -> FetchRecord {
+        let fetched_at = Timestamp::new(fetched_at, 0).unwrap();
+        FetchRecord {
+            id: None,
+            config: 0,
+            fetched_at,
+            created_at: fetched_at,
+            source_url: "https://example.com".to_owned(),
+            status,
+            body_text: None,
+            valid_json: None,
+            latency_ms: None,
+            maintenance: false,
+            assertions: None,
+            manual: false,
+            from_db: true,
+        }
+    }
+
+    fn at(second: i64) -> Timestamp {
+        Timestamp::new(second, 0).unwrap()
+    }
+
+    #[test]
+    fn statuses_last_until_the_next_record() {
+        let records = [record(100, Status::HttpOk(200)), record(200, Status::Error)];
+        let segments = segments(&records, &config(Some(250)), at(0), at(1000));
+        assert_eq!(
+            segments,
+            vec![
+                (100, 200, Status::HttpOk(200), false),
+                (310, 1000, Status::Unknown, false),
+                (200, 310, Status::Error, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn segments_are_clipped_to_the_window() {
+        let records = [record(50, Status::HttpOk(200)), record(150, Status::Error)];
+        let segments = segments(&records, &config(Some(900)), at(100), at(200));
+        assert_eq!(
+            segments,
+            vec![
+                (100, 150, Status::HttpOk(200), false),
+                (200, 200, Status::Unknown, false),
+                (150, 200, Status::Error, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_window_ending_before_it_starts_is_empty() {
+        let records = [record(50, Status::HttpOk(200))];
+        let segments = segments(&records, &config(Some(60)), at(1000), at(500));
+        let (totals, days) = split_days(&segments, UnknownAs::Exclude);
+        assert_eq!(totals.up_s + totals.down_s + totals.unknown_s, 0);
+        assert!(days.is_empty());
+    }
+
+    #[test]
+    fn segments_are_split_per_utc_day() {
+        let segments = vec![
+            (DAY_S - 100, DAY_S + 50, Status::HttpOk(200), false),
+            (DAY_S + 50, DAY_S + 100, Status::HttpErr(500), false),
+            (DAY_S + 100, DAY_S + 200, Status::Unknown, false),
+            (DAY_S + 200, DAY_S + 300, Status::HttpErr(500), true),
+        ];
+        let (totals, days) = split_days(&segments, UnknownAs::Exclude);
+        assert_eq!(totals.up_s, 150);
+        assert_eq!(totals.down_s, 50);
+        assert_eq!(totals.unknown_s, 100);
+        assert_eq!(totals.maintenance_s, 100);
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].totals.up_s, 100);
+        assert_eq!(days[0].availability_percent, Some(100.0));
+        assert_eq!(days[1].totals.up_s, 50);
+        assert_eq!(days[1].totals.down_s, 50);
+        assert_eq!(days[1].availability_percent, Some(50.0));
+    }
+
+    #[test]
+    fn maintenance_without_a_state_change_is_excluded() {
+        let records = [record(0, Status::HttpOk(200))];
+        let segments = overlay_maintenance(
+            &segments(&records, &config(Some(1000)), at(0), at(1000)),
+            &[(100, 200)],
+        );
+        let (totals, _) = split_days(&segments, UnknownAs::Exclude);
+        assert_eq!(totals.up_s, 900);
+        assert_eq!(totals.maintenance_s, 100);
+    }
+
+    #[test]
+    fn maintenance_ending_mid_segment_only_covers_the_window() {
+        let records = [record(0, Status::HttpOk(200)), record(150, Status::Error)];
+        let segments = overlay_maintenance(
+            &segments(&records, &config(Some(1000)), at(0), at(1000)),
+            &[(100, 200)],
+        );
+        assert_eq!(
+            segments,
+            vec![
+                (0, 100, Status::HttpOk(200), false),
+                (100, 150, Status::HttpOk(200), true),
+                (1000, 1000, Status::Unknown, false),
+                (150, 200, Status::Error, true),
+                (200, 1000, Status::Error, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn records_flagged_as_maintenance_dont_extend_the_window() {
+        let mut during = record(100, Status::Error);
+        during.maintenance = true;
+        let records = [record(0, Status::HttpOk(200)), during];
+        let segments = overlay_maintenance(
+            &segments(&records, &config(Some(1000)), at(0), at(1000)),
+            &[(100, 200)],
+        );
+        let (totals, _) = split_days(&segments, UnknownAs::Exclude);
+        assert_eq!(totals.up_s, 100);
+        assert_eq!(totals.maintenance_s, 100);
+        assert_eq!(totals.down_s, 800);
+    }
+
+    #[test]
+    fn unknown_time_is_counted_as_asked() {
+        let totals = UptimeTotals {
+            up_s: 50,
+            down_s: 0,
+            unknown_s: 50,
+            maintenance_s: 0,
+        };
+        assert_eq!(totals.availability_percent(UnknownAs::Exclude), Some(100.0));
+        assert_eq!(totals.availability_percent(UnknownAs::Down), Some(50.0));
+        assert_eq!(totals.availability_percent(UnknownAs::Up), Some(100.0));
+    }
+}